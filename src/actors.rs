@@ -4,35 +4,36 @@ extern crate rand;
 use ggez::{Context, GameResult};
 use ggez::graphics::{Point2, Vector2};
 use ggez::nalgebra as na;
+
+use oorandom::Rand32;
+
+use ecs::{self, Entity, System};
 use math;
 
 /// *********************************************************************
 /// Now we define our Actor's.
-/// An Actor is anything in the game world.
-/// We're not *quite* making a real entity-component system but it's
-/// pretty close.  For a more complicated game you would want a
-/// real ECS, but for this it's enough to say that all our game objects
-/// contain pretty much the same data.
+/// An Actor is anything in the game world.  The data that used to live in a
+/// single fat `Actor` struct is now split across the components in `ecs`, so
+/// "is a rock" and "has hit points" are separate facts and the movement /
+/// wrapping / timed-life code is shared with the boids instead of being
+/// copy-pasted per object.
 /// **********************************************************************
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActorType {
     Player,
     Rock,
     Shot,
 }
 
-#[derive(Debug)]
-pub struct Actor {
-    pub tag: ActorType,
-    pub pos: Point2,
-    pub facing: f32,
-    pub velocity: Vector2,
-    pub ang_vel: f32,
-    pub bbox_size: f32,
-
-    // I am going to lazily overload "life" with a double meaning:
-    // for shots, it is the time left to live, for players and rocks, it is the actual hit points.
-    pub life: f32,
+/// *********************************************************************
+/// Gameplay events the manager surfaces so callers (audio, HUD, score) can
+/// react to state changes without the manager having to know about them.
+/// **********************************************************************
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    ShotFired,
+    RockDestroyed { pos: Point2 },
+    PlayerDied,
 }
 
 const PLAYER_LIFE: f32 = 1.0;
@@ -46,219 +47,299 @@ const SHOT_BBOX: f32 = 6.0;
 const MAX_ROCK_VEL: f32 = 50.0;
 
 pub struct ActorManager {
-    player: Actor,
-    shots: Vec<Actor>,
-    rocks: Vec<Actor>,
+    world: ecs::Manager,
+    player: Entity,
+    forces: ecs::ForceSystem,
+    movement: ecs::MovementSystem,
+    wrapping: ecs::WrapSystem,
+    timed_life: ecs::TimedLifeSystem,
+    /// Real time carried over between fixed steps.
+    accumulator: f32,
+    /// Fraction of a step left over, in `[0, 1)`, for render interpolation.
+    alpha: f32,
 }
 
 impl ActorManager {
     pub fn new() -> Self {
-        let player = create_player();
-        let shots = Vec::new();
-        let rocks = create_rocks(0, player.pos, 100.0, 250.0);
+        let mut world = ecs::Manager::new();
+        let player = spawn_player(&mut world);
 
         ActorManager {
+            world,
             player,
-            shots,
-            rocks,
+            forces: ecs::ForceSystem { field: ecs::ForceField::new() },
+            movement: ecs::MovementSystem { max_speed: MAX_PHYSICS_VEL },
+            wrapping: ecs::WrapSystem { bounds: na::zero() },
+            timed_life: ecs::TimedLifeSystem,
+            accumulator: 0.0,
+            alpha: 0.0,
         }
     }
 
-    pub fn update(&mut self, seconds: f32, input: &InputState, screen_width: f32, screen_height: f32) {
-        // Update the player state based on the user input.
-        fn player_thrust(actor: &mut Actor, dt: f32) {
-            let direction_vector = math::vec_from_angle(actor.facing);
-            let thrust_vector = direction_vector * (PLAYER_THRUST);
-            actor.velocity += thrust_vector * (dt);
-        }
-        fn player_handle_input(actor: &mut Actor, input: &InputState, dt: f32) {
-            actor.facing += dt * PLAYER_TURN_RATE * input.xaxis;
+    pub fn update(&mut self, real_dt: f32, input: &InputState, screen_width: f32, screen_height: f32) {
+        self.wrapping.bounds = Vector2::new(screen_width, screen_height);
 
-            if input.yaxis > 0.0 {
-                player_thrust(actor, dt);
-            }
+        // Advance in fixed `DT` increments so motion is frame-rate
+        // independent; `alpha` holds the leftover for interpolation.
+        self.accumulator += real_dt;
+        while self.accumulator >= ecs::DT {
+            self.step(ecs::DT, input);
+            self.accumulator -= ecs::DT;
         }
-        player_handle_input(&mut self.player, &input, seconds);
+        self.alpha = self.accumulator / ecs::DT;
+    }
 
-        // Update the physics for all actors.
+    /// One fixed physics step: apply input, then the shared integrate / wrap /
+    /// decay systems that drive the player, the shots and the rocks alike.
+    fn step(&mut self, dt: f32, input: &InputState) {
+        // The player's facing before this step's turn, so `PrevFacing` holds
+        // the start-of-step angle rather than the already-turned one.
+        let start_facing = self.world.get::<ecs::Facing>(self.player).map(|f| f.angle);
         {
-            fn update_actor_position(actor: &mut Actor, dt: f32) {
-                // Clamp the velocity to the max efficiently
-                let norm_sq = actor.velocity.norm_squared();
-                if norm_sq > MAX_PHYSICS_VEL.powi(2) {
-                    actor.velocity = actor.velocity / norm_sq.sqrt() * MAX_PHYSICS_VEL;
-                }
-                let dv = actor.velocity * (dt);
-                actor.pos += dv;
-                actor.facing += actor.ang_vel;
-            }
-            /// Takes an actor and wraps its position to the bounds of the
-            /// screen, so if it goes off the left side of the screen it
-            /// will re-enter on the right side and so on.
-            fn wrap_actor_position(actor: &mut Actor, sx: f32, sy: f32) {
-                // Wrap screen
-                let screen_x_bounds = sx / 2.0;
-                let screen_y_bounds = sy / 2.0;
-                if actor.pos.x > screen_x_bounds {
-                    actor.pos.x -= sx;
-                } else if actor.pos.x < -screen_x_bounds {
-                    actor.pos.x += sx;
-                };
-                if actor.pos.y > screen_y_bounds {
-                    actor.pos.y -= sy;
-                } else if actor.pos.y < -screen_y_bounds {
-                    actor.pos.y += sy;
-                }
+            let facing = self.world.column_mut::<ecs::Facing>();
+            if let Some(f) = facing[self.player].as_mut() {
+                f.angle += dt * PLAYER_TURN_RATE * input.xaxis;
             }
-
-            // First the player...
-            update_actor_position(&mut self.player, seconds);
-            wrap_actor_position(
-                &mut self.player,
-                screen_width as f32,
-                screen_height as f32,
-            );
-
-            // Then the shots...
-            for act in &mut self.shots {
-                update_actor_position(act, seconds);
-                wrap_actor_position(act, screen_width as f32, screen_height as f32);
-                //handle_timed_life
-                act.life -= seconds
+        }
+        if input.yaxis > 0.0 {
+            let angle = self.world.get::<ecs::Facing>(self.player).map(|f| f.angle).unwrap_or(0.0);
+            let thrust = math::vec_from_angle(angle) * PLAYER_THRUST;
+            let vel = self.world.column_mut::<ecs::Velocity>();
+            if let Some(v) = vel[self.player].as_mut() {
+                v.0 += thrust * dt;
             }
+        }
 
-            // And finally the rocks.
-            for act in &mut self.rocks {
-                update_actor_position(act, seconds);
-                wrap_actor_position(act, screen_width as f32, screen_height as f32);
+        self.forces.update(&mut self.world, dt);
+        self.movement.update(&mut self.world, dt);
+        if let Some(angle) = start_facing {
+            if let Some(p) = self.world.column_mut::<ecs::PrevFacing>()[self.player].as_mut() {
+                p.0 = angle;
             }
         }
+        self.wrapping.update(&mut self.world, dt);
+        self.timed_life.update(&mut self.world, dt);
     }
 
-    pub fn draw(&mut self, ctx: &mut Context, assets: &super::Assets, coords: (u32, u32)) -> GameResult<()> {
-        let p = &self.player;
-        super::draw_actor(assets, ctx, p, coords)?;
-
-        for s in &self.shots {
-            super::draw_actor(assets, ctx, s, coords)?;
-        }
+    /// Mutable access to the global/local force field (gravity, radial
+    /// sources, drag) so callers can make rocks fall or add attractors.
+    pub fn forces_mut(&mut self) -> &mut ecs::ForceField {
+        &mut self.forces.field
+    }
 
-        for r in &self.rocks {
-            super::draw_actor(assets, ctx, r, coords)?;
+    pub fn draw(&mut self, ctx: &mut Context, assets: &super::Assets, coords: (u32, u32)) -> GameResult<()> {
+        let bounds = Vector2::new(coords.0 as f32, coords.1 as f32);
+        let positions = self.world.column::<ecs::Position>();
+        let prev_positions = self.world.column::<ecs::PrevPosition>();
+        let facings = self.world.column::<ecs::Facing>();
+        let prev_facings = self.world.column::<ecs::PrevFacing>();
+        let sprites = self.world.column::<ecs::Sprite>();
+        for e in self.world.entities() {
+            if !self.world.is_alive(e) {
+                continue;
+            }
+            if let (Some(&Some(pos)), Some(Some(sprite))) = (positions.get(e), sprites.get(e)) {
+                // Render between the previous and current fixed-step state.
+                let drawn = match prev_positions.get(e).and_then(|p| p.as_ref()) {
+                    Some(prev) => math::lerp_wrapped(&prev.0, &pos.0, self.alpha, &bounds),
+                    None => pos.0,
+                };
+                let facing = facings.get(e).and_then(|f| f.as_ref()).map(|f| f.angle).unwrap_or(0.0);
+                let facing = match prev_facings.get(e).and_then(|f| f.as_ref()) {
+                    Some(prev) => prev.0 + (facing - prev.0) * self.alpha,
+                    None => facing,
+                };
+                super::draw_actor(assets, ctx, &sprite.0, drawn, facing, coords)?;
+            }
         }
-
         Ok(())
     }
 
     pub fn rocks_are_empty(&mut self) -> bool {
-        self.rocks.is_empty()
+        self.entities_of(ActorType::Rock).is_empty()
     }
 
-    pub fn when_rocks_empty(&mut self, _new_level: i32) {
-        //let r = create_rocks(new_level, self.player.pos, 100.0, 250.0);
-        //self.rocks.extend(r);
+    pub fn when_rocks_empty(&mut self, rng: &mut Rand32, new_level: i32) {
+        let player_pos = self.world.get::<ecs::Position>(self.player).map(|p| p.0).unwrap_or_else(Point2::origin);
+        spawn_rocks(&mut self.world, rng, new_level, player_pos, 100.0, 250.0);
     }
 
     pub fn player_is_dead(&mut self) -> bool {
-        self.player.life <= 0.0
+        self.world.get::<ecs::Health>(self.player).map_or(true, |h| h.0 <= 0.0)
     }
 
-    pub fn fire_player_shot_helper(&mut self) {
-        let player: &Actor = &self.player;
-
-        let player = &player;
-        let mut shot = create_shot();
-        shot.pos = player.pos;
-        shot.facing = player.facing;
-        let direction = math::vec_from_angle(shot.facing);
-        shot.velocity.x = SHOT_SPEED * direction.x;
-        shot.velocity.y = SHOT_SPEED * direction.y;
+    pub fn fire_player_shot_helper(&mut self) -> Vec<GameEvent> {
+        let pos = self.world.get::<ecs::Position>(self.player).map(|p| p.0).unwrap_or_else(Point2::origin);
+        let facing = self.world.get::<ecs::Facing>(self.player).map(|f| f.angle).unwrap_or(0.0);
+        let shot = spawn_shot(&mut self.world);
+        let direction = math::vec_from_angle(facing);
+        if let Some(p) = self.world.column_mut::<ecs::Position>()[shot].as_mut() {
+            p.0 = pos;
+        }
+        if let Some(f) = self.world.column_mut::<ecs::Facing>()[shot].as_mut() {
+            f.angle = facing;
+        }
+        if let Some(v) = self.world.column_mut::<ecs::Velocity>()[shot].as_mut() {
+            v.0 = Vector2::new(SHOT_SPEED * direction.x, SHOT_SPEED * direction.y);
+        }
 
-        self.shots.push(shot);
+        vec![GameEvent::ShotFired]
     }
 
     pub fn clear_dead_stuff(&mut self) {
-        self.shots.retain(|s| s.life > 0.0);
-        self.rocks.retain(|r| r.life > 0.0);
+        let reap: Vec<Entity> = self
+            .world
+            .entities()
+            .filter(|&e| e != self.player && self.world.is_alive(e))
+            .filter(|&e| {
+                let timed_out = self.world.get::<ecs::TimedLife>(e).map_or(false, |l| l.0 <= 0.0);
+                let destroyed = self.world.get::<ecs::Health>(e).map_or(false, |h| h.0 <= 0.0);
+                timed_out || destroyed
+            })
+            .collect();
+        for e in reap {
+            self.world.kill(e);
+        }
     }
 
-    pub fn handle_collisions(&mut self) -> i32 {
-        let mut num_hits = 0;
-        for rock in &mut self.rocks {
-            let pdistance = rock.pos - self.player.pos;
-            if pdistance.norm() < (self.player.bbox_size + rock.bbox_size) {
-                self.player.life = 0.0;
+    pub fn handle_collisions(&mut self) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let rocks = self.entities_of(ActorType::Rock);
+        let shots = self.entities_of(ActorType::Shot);
+
+        let ppos = self.world.get::<ecs::Position>(self.player).map(|p| p.0).unwrap_or_else(Point2::origin);
+        let pbbox = self.world.get::<ecs::Collider>(self.player).map_or(0.0, |c| c.0);
+
+        for &rock in &rocks {
+            let rpos = self.world.get::<ecs::Position>(rock).unwrap().0;
+            let rbbox = self.world.get::<ecs::Collider>(rock).unwrap().0;
+
+            if (rpos - ppos).norm() < (pbbox + rbbox) {
+                if let Some(h) = self.world.column_mut::<ecs::Health>()[self.player].as_mut() {
+                    h.0 = 0.0;
+                }
+                events.push(GameEvent::PlayerDied);
             }
-            for shot in &mut self.shots {
-                let distance = shot.pos - rock.pos;
-                if distance.norm() < (shot.bbox_size + rock.bbox_size) {
-                    shot.life = 0.0;
-                    rock.life = 0.0;
-                    num_hits += 1;
+            for &shot in &shots {
+                let spos = self.world.get::<ecs::Position>(shot).unwrap().0;
+                let sbbox = self.world.get::<ecs::Collider>(shot).unwrap().0;
+                if (spos - rpos).norm() < (sbbox + rbbox) {
+                    if let Some(l) = self.world.column_mut::<ecs::TimedLife>()[shot].as_mut() {
+                        l.0 = 0.0;
+                    }
+                    if let Some(h) = self.world.column_mut::<ecs::Health>()[rock].as_mut() {
+                        h.0 = 0.0;
+                    }
+                    events.push(GameEvent::RockDestroyed { pos: rpos });
                 }
             }
         }
-        num_hits
+        events
+    }
+
+    /// Position, facing and velocity of the player, for anything that wants
+    /// to steer the ship (the neural autopilot) or aim at it.
+    pub fn player_kinematics(&self) -> (Point2, f32, Vector2) {
+        let pos = self.world.get::<ecs::Position>(self.player).map(|p| p.0).unwrap_or_else(Point2::origin);
+        let facing = self.world.get::<ecs::Facing>(self.player).map(|f| f.angle).unwrap_or(0.0);
+        let vel = self.world.get::<ecs::Velocity>(self.player).map(|v| v.0).unwrap_or_else(na::zero);
+        (pos, facing, vel)
+    }
+
+    /// The `(position, bounding radius)` of every live rock, for raycast
+    /// sensors and other spatial queries.
+    pub fn rock_colliders(&self) -> Vec<(Point2, f32)> {
+        self.entities_of(ActorType::Rock)
+            .into_iter()
+            .map(|e| {
+                let pos = self.world.get::<ecs::Position>(e).unwrap().0;
+                let bbox = self.world.get::<ecs::Collider>(e).unwrap().0;
+                (pos, bbox)
+            })
+            .collect()
+    }
+
+    /// Spawn a fresh field of rocks around the player, as a level start does.
+    /// Handy for driving headless simulations during training.
+    pub fn spawn_level(&mut self, rng: &mut Rand32, num: i32) {
+        let player_pos = self.world.get::<ecs::Position>(self.player).map(|p| p.0).unwrap_or_else(Point2::origin);
+        spawn_rocks(&mut self.world, rng, num, player_pos, 100.0, 250.0);
+    }
+
+    /// Handle to every live entity of a given kind.
+    fn entities_of(&self, kind: ActorType) -> Vec<Entity> {
+        let kinds = self.world.column::<ActorType>();
+        self.world
+            .entities()
+            .filter(|&e| self.world.is_alive(e))
+            .filter(|&e| kinds.get(e).and_then(|k| k.as_ref()) == Some(&kind))
+            .collect()
     }
 }
 
 /// *********************************************************************
 /// Now we have some constructor functions for different game objects.
+/// Each one allocates an entity and hangs the right components off it.
 /// **********************************************************************
 
-pub fn create_player() -> Actor {
-    Actor {
-        tag: ActorType::Player,
-        pos: Point2::origin(),
-        facing: 0.,
-        velocity: na::zero(),
-        ang_vel: 0.,
-        bbox_size: PLAYER_BBOX,
-        life: PLAYER_LIFE,
-    }
+pub fn spawn_player(world: &mut ecs::Manager) -> Entity {
+    let e = world.create_entity();
+    world.add_component_direct(e, ecs::Position(Point2::origin()));
+    world.add_component_direct(e, ecs::Velocity(na::zero()));
+    world.add_component_direct(e, ecs::Facing { angle: 0.0, ang_vel: 0.0 });
+    world.add_component_direct(e, ecs::Collider(PLAYER_BBOX));
+    world.add_component_direct(e, ecs::Health(PLAYER_LIFE));
+    world.add_component_direct(e, ecs::Wrapping);
+    world.add_component_direct(e, ecs::Sprite("player".to_string()));
+    world.add_component_direct(e, ActorType::Player);
+    e
 }
 
-pub fn create_rock() -> Actor {
-    Actor {
-        tag: ActorType::Rock,
-        pos: Point2::origin(),
-        facing: 0.,
-        velocity: na::zero(),
-        ang_vel: 0.,
-        bbox_size: ROCK_BBOX,
-        life: ROCK_LIFE,
-    }
+pub fn spawn_rock(world: &mut ecs::Manager) -> Entity {
+    let e = world.create_entity();
+    world.add_component_direct(e, ecs::Position(Point2::origin()));
+    world.add_component_direct(e, ecs::Velocity(na::zero()));
+    world.add_component_direct(e, ecs::Facing { angle: 0.0, ang_vel: 0.0 });
+    world.add_component_direct(e, ecs::Collider(ROCK_BBOX));
+    world.add_component_direct(e, ecs::Health(ROCK_LIFE));
+    world.add_component_direct(e, ecs::Wrapping);
+    world.add_component_direct(e, ecs::Sprite("rock".to_string()));
+    world.add_component_direct(e, ActorType::Rock);
+    e
 }
 
-pub fn create_shot() -> Actor {
-    Actor {
-        tag: ActorType::Shot,
-        pos: Point2::origin(),
-        facing: 0.,
-        velocity: na::zero(),
-        ang_vel: SHOT_ANG_VEL,
-        bbox_size: SHOT_BBOX,
-        life: SHOT_LIFE,
-    }
+pub fn spawn_shot(world: &mut ecs::Manager) -> Entity {
+    let e = world.create_entity();
+    world.add_component_direct(e, ecs::Position(Point2::origin()));
+    world.add_component_direct(e, ecs::Velocity(na::zero()));
+    world.add_component_direct(e, ecs::Facing { angle: 0.0, ang_vel: SHOT_ANG_VEL });
+    world.add_component_direct(e, ecs::Collider(SHOT_BBOX));
+    world.add_component_direct(e, ecs::Wrapping);
+    world.add_component_direct(e, ecs::TimedLife(SHOT_LIFE));
+    world.add_component_direct(e, ecs::Sprite("shot".to_string()));
+    world.add_component_direct(e, ActorType::Shot);
+    e
 }
 
-/// Create the given number of rocks.
+/// Create the given number of rocks as entities in `world`.
 /// Makes sure that none of them are within the
 /// given exclusion zone (nominally the player)
 /// Note that this *could* create rocks outside the
 /// bounds of the playing field, so it should be
-/// called before `wrap_actor_position()` happens.
-pub fn create_rocks(num: i32, exclusion: Point2, min_radius: f32, max_radius: f32) -> Vec<Actor> {
+/// called before wrapping happens.
+pub fn spawn_rocks(world: &mut ecs::Manager, rng: &mut Rand32, num: i32, exclusion: Point2, min_radius: f32, max_radius: f32) {
     assert!(max_radius > min_radius);
-    let new_rock = |_| {
-        let mut rock = create_rock();
-        let r_angle = rand::random::<f32>() * 2.0 * ::std::f32::consts::PI;
-        let r_distance = rand::random::<f32>() * (max_radius - min_radius) + min_radius;
-        rock.pos = exclusion + math::vec_from_angle(r_angle) * r_distance;
-        rock.velocity = math::random_vec(MAX_ROCK_VEL);
-        rock
-    };
-    (0..num).map(new_rock).collect()
+    for _ in 0..num {
+        let rock = spawn_rock(world);
+        let r_angle = rng.rand_float() * 2.0 * ::std::f32::consts::PI;
+        let r_distance = rng.rand_float() * (max_radius - min_radius) + min_radius;
+        if let Some(p) = world.column_mut::<ecs::Position>()[rock].as_mut() {
+            p.0 = exclusion + math::vec_from_angle(r_angle) * r_distance;
+        }
+        if let Some(v) = world.column_mut::<ecs::Velocity>()[rock].as_mut() {
+            v.0 = math::random_vec_seeded(rng, MAX_ROCK_VEL);
+        }
+    }
 }
 
 /// *********************************************************************
@@ -288,7 +369,7 @@ const MAX_PHYSICS_VEL: f32 = 250.0;
 /// the user's input state so that we turn keyboard events into something
 /// state-based and device-independent.
 /// **********************************************************************
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InputState {
     pub xaxis: f32,
     pub yaxis: f32,
@@ -0,0 +1,357 @@
+//! A neural-network autopilot for the player ship, trained by a genetic
+//! algorithm to dodge rocks and shoot them.
+//!
+//! The controller replaces `InputState` coming from the keyboard: every frame
+//! it fans a bank of raycast sensors out around the ship's facing, measures
+//! how far away the nearest rock is along each ray, and feeds those distances
+//! (plus the ship's speed) through a tiny fixed-topology feed-forward network.
+//! The three outputs map back onto the same `xaxis` / `yaxis` / `fire` an
+//! `InputState` carries, so the rest of the game does not know or care whether
+//! a human or a genome is flying.
+
+extern crate rand;
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use ggez::graphics::{Point2, Vector2};
+use oorandom::Rand32;
+
+use actors::{self, ActorManager, GameEvent, InputState};
+
+/// Number of raycast sensors fanned around the ship's facing.
+pub const NUM_RAYS: usize = 7;
+/// Neurons in the single hidden layer.
+pub const HIDDEN: usize = 8;
+/// Inputs: one normalised distance per ray, plus the ship's speed.
+pub const INPUTS: usize = NUM_RAYS + 1;
+/// Outputs: turn, thrust, fire threshold.
+pub const OUTPUTS: usize = 3;
+
+/// Half-angle of the sensor fan, in radians.
+const FAN_HALF_ANGLE: f32 = 1.0;
+/// Distance past which a ray is considered to have hit nothing.
+const RAY_RANGE: f32 = 400.0;
+/// Speed the velocity-magnitude input is normalised against.
+const SPEED_NORM: f32 = 250.0;
+
+/// *********************************************************************
+/// The network.  A single hidden layer with a `tanh` activation is plenty
+/// for a reactive dodge-and-shoot policy, and keeping the topology fixed
+/// means a genome is just the flattened weight vector.
+/// **********************************************************************
+#[derive(Clone)]
+pub struct NeuralNet {
+    // Row-major weight matrices with a trailing bias column.
+    hidden_weights: Vec<f32>, // HIDDEN * (INPUTS + 1)
+    output_weights: Vec<f32>, // OUTPUTS * (HIDDEN + 1)
+}
+
+impl NeuralNet {
+    /// A network with all weights drawn uniformly from `[-1, 1]`.
+    pub fn random() -> NeuralNet {
+        let rand_weight = || rand::random::<f32>() * 2.0 - 1.0;
+        NeuralNet {
+            hidden_weights: (0..HIDDEN * (INPUTS + 1)).map(|_| rand_weight()).collect(),
+            output_weights: (0..OUTPUTS * (HIDDEN + 1)).map(|_| rand_weight()).collect(),
+        }
+    }
+
+    /// Build a network from a flattened genome (hidden weights followed by
+    /// output weights).
+    pub fn from_genome(genome: &[f32]) -> NeuralNet {
+        let split = HIDDEN * (INPUTS + 1);
+        NeuralNet {
+            hidden_weights: genome[..split].to_vec(),
+            output_weights: genome[split..].to_vec(),
+        }
+    }
+
+    /// The flattened weight vector, for crossover and persistence.
+    pub fn genome(&self) -> Vec<f32> {
+        let mut g = self.hidden_weights.clone();
+        g.extend_from_slice(&self.output_weights);
+        g
+    }
+
+    /// Total number of weights in a genome.
+    pub fn genome_len() -> usize {
+        HIDDEN * (INPUTS + 1) + OUTPUTS * (HIDDEN + 1)
+    }
+
+    /// Forward pass.  Returns the raw output layer.
+    fn feed(&self, inputs: &[f32]) -> [f32; OUTPUTS] {
+        let mut hidden = [0.0f32; HIDDEN];
+        for (h, hv) in hidden.iter_mut().enumerate() {
+            let base = h * (INPUTS + 1);
+            let mut sum = self.hidden_weights[base + INPUTS]; // bias
+            for i in 0..INPUTS {
+                sum += self.hidden_weights[base + i] * inputs[i];
+            }
+            *hv = sum.tanh();
+        }
+
+        let mut out = [0.0f32; OUTPUTS];
+        for (o, ov) in out.iter_mut().enumerate() {
+            let base = o * (HIDDEN + 1);
+            let mut sum = self.output_weights[base + HIDDEN]; // bias
+            for h in 0..HIDDEN {
+                sum += self.output_weights[base + h] * hidden[h];
+            }
+            *ov = sum.tanh();
+        }
+        out
+    }
+}
+
+/// *********************************************************************
+/// The controller: sense the world, run the net, produce an `InputState`.
+/// **********************************************************************
+pub struct NeuralController {
+    pub net: NeuralNet,
+}
+
+impl NeuralController {
+    pub fn new(net: NeuralNet) -> NeuralController {
+        NeuralController { net }
+    }
+
+    /// Produce the next frame's input for the player in `mgr`.
+    pub fn update(&self, mgr: &ActorManager) -> InputState {
+        let (pos, facing, velocity) = mgr.player_kinematics();
+        let rocks = mgr.rock_colliders();
+
+        let mut inputs = [0.0f32; INPUTS];
+        for (i, slot) in inputs.iter_mut().take(NUM_RAYS).enumerate() {
+            // Fan the rays evenly across the field of view.
+            let t = if NUM_RAYS > 1 {
+                i as f32 / (NUM_RAYS - 1) as f32 * 2.0 - 1.0
+            } else {
+                0.0
+            };
+            let angle = facing + t * FAN_HALF_ANGLE;
+            *slot = nearest_hit(pos, angle, &rocks);
+        }
+        inputs[NUM_RAYS] = (velocity.norm() / SPEED_NORM).min(1.0);
+
+        let out = self.net.feed(&inputs);
+        InputState {
+            xaxis: out[0],
+            yaxis: if out[1] > 0.0 { 1.0 } else { 0.0 },
+            fire: out[2] > 0.0,
+        }
+    }
+}
+
+/// Cast a ray from `origin` along `angle` and return the nearest rock
+/// intersection distance, normalised to `[0, 1]` with no-hit mapping to 1.0.
+fn nearest_hit(origin: Point2, angle: f32, rocks: &[(Point2, f32)]) -> f32 {
+    // A unit direction using the same convention as `math::vec_from_angle`.
+    let dir = Vector2::new(angle.sin(), angle.cos());
+    let mut nearest = ::std::f32::MAX;
+
+    for &(center, radius) in rocks {
+        // Solve |origin + t*dir - center|^2 = radius^2 for the smallest t >= 0.
+        let m = origin - center;
+        let b = m.dot(&dir);
+        let c = m.dot(&m) - radius * radius;
+        // Ray starts outside and points away: no hit.
+        if c > 0.0 && b > 0.0 {
+            continue;
+        }
+        let discr = b * b - c;
+        if discr < 0.0 {
+            continue;
+        }
+        let t = -b - discr.sqrt();
+        let t = if t < 0.0 { 0.0 } else { t };
+        if t < nearest {
+            nearest = t;
+        }
+    }
+
+    if nearest >= ::std::f32::MAX {
+        1.0
+    } else {
+        (nearest / RAY_RANGE).min(1.0)
+    }
+}
+
+/// *********************************************************************
+/// Genetic training.  A population of genomes each fly an independent,
+/// headless `ActorManager`; the fittest breed the next generation.
+/// **********************************************************************
+
+/// Reward for destroying a rock, relative to one second of survival.
+const FITNESS_KILL_BONUS: f32 = 3.0;
+/// Probability that any single weight is perturbed during mutation.
+const MUTATION_RATE: f32 = 0.05;
+/// Standard deviation of the Gaussian mutation applied to a weight.
+const MUTATION_STDDEV: f32 = 0.3;
+
+pub struct TrainParams {
+    pub population: usize,
+    pub generations: usize,
+    pub rocks: i32,
+    pub max_seconds: f32,
+    pub dt: f32,
+}
+
+impl Default for TrainParams {
+    fn default() -> Self {
+        TrainParams {
+            population: 50,
+            generations: 30,
+            rocks: 8,
+            max_seconds: 30.0,
+            dt: 1.0 / 60.0,
+        }
+    }
+}
+
+/// Run the full genetic algorithm and return the best genome found.
+pub fn train(params: &TrainParams) -> Vec<f32> {
+    let mut population: Vec<Vec<f32>> = (0..params.population)
+        .map(|_| NeuralNet::random().genome())
+        .collect();
+
+    let mut best = population[0].clone();
+    let mut best_fitness = ::std::f32::MIN;
+
+    for _gen in 0..params.generations {
+        let scored: Vec<(f32, Vec<f32>)> = population
+            .iter()
+            .map(|g| (evaluate(g, params), g.clone()))
+            .collect();
+
+        for &(fitness, ref genome) in &scored {
+            if fitness > best_fitness {
+                best_fitness = fitness;
+                best = genome.clone();
+            }
+        }
+
+        population = next_generation(&scored);
+    }
+
+    best
+}
+
+/// Score a single genome by flying a headless simulation.
+fn evaluate(genome: &[f32], params: &TrainParams) -> f32 {
+    let controller = NeuralController::new(NeuralNet::from_genome(genome));
+    let mut mgr = ActorManager::new();
+    // A fixed seed so every genome faces the identical asteroid field, which
+    // keeps the fitness comparison fair.
+    let mut rng = Rand32::new(0xa5702b1c);
+    mgr.spawn_level(&mut rng, params.rocks);
+
+    // Train in an 800x800 toroidal world, the game's default window size.
+    let bounds = 800.0;
+
+    let mut survived = 0.0;
+    let mut kills = 0;
+    let mut shot_timeout = 0.0;
+    let steps = (params.max_seconds / params.dt) as usize;
+    for _ in 0..steps {
+        let input = controller.update(&mgr);
+        // Fire on the same cooldown the game enforces, so the net's fire
+        // output actually spawns shots and kills can be scored.
+        shot_timeout -= params.dt;
+        if input.fire && shot_timeout < 0.0 {
+            shot_timeout = actors::PLAYER_SHOT_TIME;
+            mgr.fire_player_shot_helper();
+        }
+        mgr.update(params.dt, &input, bounds, bounds);
+        kills += mgr
+            .handle_collisions()
+            .iter()
+            .filter(|e| if let GameEvent::RockDestroyed { .. } = **e { true } else { false })
+            .count() as i32;
+        mgr.clear_dead_stuff();
+        if mgr.player_is_dead() {
+            break;
+        }
+        survived += params.dt;
+    }
+
+    survived + FITNESS_KILL_BONUS * kills as f32
+}
+
+/// Build the next generation from scored parents via tournament selection,
+/// single-point crossover and Gaussian mutation.
+fn next_generation(scored: &[(f32, Vec<f32>)]) -> Vec<Vec<f32>> {
+    let mut next = Vec::with_capacity(scored.len());
+    // Elitism: the single best genome survives unchanged.
+    if let Some(best) = scored.iter().max_by(|a, b| a.0.partial_cmp(&b.0).unwrap()) {
+        next.push(best.1.clone());
+    }
+    while next.len() < scored.len() {
+        let a = tournament(scored);
+        let b = tournament(scored);
+        let mut child = crossover(a, b);
+        mutate(&mut child);
+        next.push(child);
+    }
+    next
+}
+
+/// Pick the better of two random genomes.
+fn tournament<'a>(scored: &'a [(f32, Vec<f32>)]) -> &'a [f32] {
+    let i = rand::random::<usize>() % scored.len();
+    let j = rand::random::<usize>() % scored.len();
+    if scored[i].0 >= scored[j].0 {
+        &scored[i].1
+    } else {
+        &scored[j].1
+    }
+}
+
+/// Single-point crossover over the flattened weight vectors.
+fn crossover(a: &[f32], b: &[f32]) -> Vec<f32> {
+    let point = rand::random::<usize>() % a.len();
+    let mut child = Vec::with_capacity(a.len());
+    child.extend_from_slice(&a[..point]);
+    child.extend_from_slice(&b[point..]);
+    child
+}
+
+/// Perturb weights with small Gaussian noise.
+fn mutate(genome: &mut [f32]) {
+    for w in genome.iter_mut() {
+        if rand::random::<f32>() < MUTATION_RATE {
+            *w += gaussian() * MUTATION_STDDEV;
+        }
+    }
+}
+
+/// A standard-normal sample via the Box-Muller transform (we only pull in
+/// `rand`'s uniform generator elsewhere, so roll the normal by hand).
+fn gaussian() -> f32 {
+    let u1 = rand::random::<f32>().max(::std::f32::MIN_POSITIVE);
+    let u2 = rand::random::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * ::std::f32::consts::PI * u2).cos()
+}
+
+/// *********************************************************************
+/// Persistence.  Genomes are written as newline-separated floats so an
+/// "interesting pilot" can be shared as a plain text file.
+/// **********************************************************************
+pub fn save_genome(genome: &[f32], path: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    for w in genome {
+        writeln!(file, "{}", w)?;
+    }
+    Ok(())
+}
+
+pub fn load_genome(path: &str) -> io::Result<Vec<f32>> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let genome = contents
+        .lines()
+        .filter_map(|l| l.trim().parse::<f32>().ok())
+        .collect();
+    Ok(genome)
+}
@@ -0,0 +1,58 @@
+//! Maps gameplay events onto sound effects.
+//!
+//! `ActorManager` does not play anything itself; it returns a `GameEvent`
+//! stream and the main loop hands that stream here.  Keeping playback out of
+//! the manager leaves it testable and lets other consumers (score, HUD) read
+//! the same events.
+
+use std::collections::HashMap;
+
+use ggez::audio::Source;
+use ggez::{Context, GameResult};
+
+use actors::GameEvent;
+use AssetManifest;
+
+/// The logical sound name fired when a shot goes out.
+const SHOT_KEY: &str = "shot";
+/// The logical sound name shared by rock destruction and player death.
+const HIT_KEY: &str = "hit";
+
+pub struct SoundBank {
+    sounds: HashMap<String, Source>,
+}
+
+impl SoundBank {
+    pub fn new(ctx: &mut Context, manifest: &AssetManifest, volume: f32) -> GameResult<SoundBank> {
+        let mut sounds = HashMap::new();
+        for (name, path) in &manifest.sounds {
+            let mut source = Source::new(ctx, path)?;
+            source.set_volume(volume);
+            sounds.insert(name.clone(), source);
+        }
+        Ok(SoundBank { sounds })
+    }
+
+    /// Re-apply the effective volume to every loaded sound, after the user
+    /// changes it in the config.
+    pub fn set_volume(&mut self, volume: f32) {
+        for source in self.sounds.values_mut() {
+            source.set_volume(volume);
+        }
+    }
+
+    /// Play the sound for each event in the frame's stream, resolving the
+    /// event to a logical key the manifest supplies.
+    pub fn play_events(&mut self, _ctx: &mut Context, events: &[GameEvent]) {
+        for event in events {
+            let key = match *event {
+                GameEvent::ShotFired => SHOT_KEY,
+                // A destroyed rock and the player's death share the explosion.
+                GameEvent::RockDestroyed { .. } | GameEvent::PlayerDied => HIT_KEY,
+            };
+            if let Some(source) = self.sounds.get_mut(key) {
+                let _ = source.play();
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
 
+use std::collections::HashMap;
+
 use ggez::graphics::{Point2, Vector2};
 use ggez::graphics;
 use ggez::{Context, GameResult};
+
+use oorandom::Rand32;
+
+use ecs::{self, Entity, System};
 use math;
-use rand;
 
-const NUM_BOIDS:usize = 100;
 const NUM_ATTRACTORS:usize = 8;
 
 const ACCELERATION_LIMIT: f32 = 60.;
@@ -14,11 +18,8 @@ const MIN_SPEED_LIMIT: f32 = 50.;
 const _SPEED_LIMIT_SQ:f32 = SPEED_LIMIT * SPEED_LIMIT;
 
 const SEPARATION_DISTANCE:f32 = 40.;
-const SEP_DIST_SQ:f32 = SEPARATION_DISTANCE * SEPARATION_DISTANCE;
 const COHESION_DISTANCE:f32 = 200.;
-const COH_DIST_SQ:f32 = COHESION_DISTANCE * COHESION_DISTANCE;
 const ALIGNMENT_DISTANCE:f32 = 200.;
-const ALI_DIST_SQ:f32 = ALIGNMENT_DISTANCE * ALIGNMENT_DISTANCE;
 
 const SEPARATION_FORCE:f32 = 10.15;
 const COHESION_FORCE:f32 = 0.1;
@@ -27,168 +28,389 @@ const ALIGNMENT_FORCE:f32 = 0.25;
 const ATTRACTOR_RADIUS:f32 = 150.;
 const ATTRACTOR_FORCE:f32 = 0.525;
 
-pub struct Attractors {
-    position: Vec<Point2>,
-    radius: Vec<f32>,
-    force: Vec<f32>,
+const FLEE_RADIUS:f32 = 120.;
+const FLEE_FORCE:f32 = 1.5;
+
+/// Live-tunable flocking parameters.  `FlockingSystem::update` reads these
+/// each frame, so the debug overlay can mutate them and the flock's behaviour
+/// changes immediately.  The defaults reproduce the original hard-coded
+/// constants.
+#[derive(Clone, Copy)]
+pub struct FlockParams {
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub separation_radius: f32,
+    pub neighbor_radius: f32,
+    pub max_speed: f32,
+    pub max_force: f32,
+    /// Distance within which a boid flees a predator (the player ship, and
+    /// optionally rocks).
+    pub flee_radius: f32,
+    /// Strength of the flee steering.  Kept above `cohesion_weight` so a boid
+    /// would rather scatter from the ship than stick with the flock.
+    pub flee_weight: f32,
 }
 
-pub struct BoidComponent {
-    position: Vec<Point2>,
-    acceleration: Vec<Vector2>,
-    velocity: Vec<Vector2>,
-    attractors: Attractors,
-}
-
-impl BoidComponent {
-
-    pub fn new() -> BoidComponent {
-        let att = Attractors {
-            position: Vec::<Point2>::with_capacity(NUM_ATTRACTORS),
-            radius: Vec::<f32>::with_capacity(NUM_ATTRACTORS),
-            force: Vec::<f32>::with_capacity(NUM_ATTRACTORS),
-        };
-
-        BoidComponent {
-            position: Vec::<Point2>::with_capacity(NUM_BOIDS),
-            acceleration: Vec::<Vector2>::with_capacity(NUM_BOIDS),
-            velocity: Vec::<Vector2>::with_capacity(NUM_BOIDS),
-            attractors: att,
-        }
-    }
-
-    pub fn init(&mut self, screen_size: &Vector2) {
-        for _ in 0..NUM_BOIDS {
-            self.spawn_random();
-        }
-        for _ in 0..NUM_ATTRACTORS {
-            self.spawn_attractor(screen_size);
+impl Default for FlockParams {
+    fn default() -> FlockParams {
+        FlockParams {
+            separation_weight: SEPARATION_FORCE,
+            alignment_weight: ALIGNMENT_FORCE,
+            cohesion_weight: COHESION_FORCE,
+            separation_radius: SEPARATION_DISTANCE,
+            neighbor_radius: COHESION_DISTANCE.max(ALIGNMENT_DISTANCE),
+            max_speed: SPEED_LIMIT,
+            max_force: ACCELERATION_LIMIT,
+            flee_radius: FLEE_RADIUS,
+            flee_weight: FLEE_FORCE,
         }
     }
+}
 
-    pub fn spawn_attractor(&mut self, screen_size: &Vector2) -> usize {
-        self.attractors.position.push(Point2::new(screen_size.x * rand::random::<f32>() - screen_size.x/2f32, screen_size.y * rand::random::<f32>() - screen_size.y/2f32));
-        self.attractors.radius.push(ATTRACTOR_RADIUS);
-        self.attractors.force.push(ATTRACTOR_FORCE);
+/// The classic Reynolds flocking rules, expressed as an `ecs::System` so the
+/// boids share the same entity/component world as everything else.  Attractors
+/// are no longer special-cased here; they are plain `ecs::ForceSource`s in a
+/// shared `ForceField` applied before the flocking step.
+pub struct FlockingSystem {
+    /// Size of the toroidal world, so neighbour queries can wrap across the
+    /// screen seam.  Refreshed by `BoidComponent::update` each frame.
+    bounds: Vector2,
+    /// Live flocking weights and radii, mutated by the debug overlay.
+    params: FlockParams,
+    /// Positions boids steer away from (the player ship, plus any obstacles
+    /// like rocks).  Refreshed by `BoidComponent::update` each frame.
+    predators: Vec<Point2>,
+}
 
-        self.attractors.position.len() - 1
+impl FlockingSystem {
+    /// Cell size for the neighbour grid: the largest perception radius, so a
+    /// boid's entire neighbourhood always lies within the surrounding 3x3
+    /// block of cells.  Exposed so callers can keep it in sync with whatever
+    /// radius the flocking params use.
+    pub fn cell_size(&self) -> f32 {
+        self.params.separation_radius.max(self.params.neighbor_radius)
     }
 
-    pub fn spawn_random(&mut self) -> usize {
-        self.position.push(Point2::new(100. * rand::random::<f32>(), 100. * rand::random::<f32>()));
-        self.acceleration.push(Vector2::new(0., 0.));
-        self.velocity.push(Vector2::new(400. * rand::random::<f32>() - 200., 400. * rand::random::<f32>() - 200.));
-        self.position.len() - 1
+    /// Number of grid cells per axis across the toroidal world, derived from
+    /// the exposed `cell_size` so the wrap stays in sync with whatever
+    /// perception radius the flocking params currently use.
+    fn grid_span(&self) -> (i32, i32) {
+        grid_span(self.bounds, self.cell_size())
     }
+}
 
-    pub fn update(&mut self, dt: f32, screen_size: &Vector2) {
-        let boids_length = self.position.len();
-
-        //println!("\n\n--------\nUPDATING BOIDS");
-        for b in 0..boids_length {
-            //println!("\nboid {}", b);
-
-            let mut s_force = Vector2::new(0., 0.);
-            let mut c_force = Vector2::new(0., 0.);
-            let mut a_force = Vector2::new(0., 0.);
-
-            for target in 0..self.attractors.position.len() {
-                let spare = self.position[b] - self.attractors.position[target];
-                //println!("spare: {}", spare);
-                let dist = spare.norm();
-                //println!("dist: {}", dist);
-                //println!("self.attractors.radius[target]: {}", self.attractors.radius[target]);
-                if dist < self.attractors.radius[target] {
-                    let length = spare.norm();
-                    let delta = Vector2::new(self.attractors.force[target] * spare.x / length, self.attractors.force[target] * spare.y / length);
-                    self.velocity[b] -= delta;
-                }
+impl System for FlockingSystem {
+    fn update(&mut self, manager: &mut ecs::Manager, dt: f32) {
+        ecs::snapshot_previous(manager);
+        // Snapshot the read-only columns so the accumulation loop does not
+        // fight the borrow checker over the acceleration column it writes.
+        // Keep the snapshots the same length as the columns and indexed by
+        // entity id: compacting out the empty slots would desync these indices
+        // from the acceleration/velocity/position columns once the free-list
+        // leaves holes behind recycled entities.
+        let position: Vec<Option<Point2>> = manager.column::<ecs::Position>().iter().map(|p| p.map(|p| p.0)).collect();
+        let velocity: Vec<Option<Vector2>> = manager.column::<ecs::Velocity>().iter().map(|v| v.map(|v| v.0)).collect();
+        let boids_length = position.len();
+
+        // Rebuild a uniform spatial hash every tick: each boid lands in the
+        // cell its position floors into.  With evenly spread flocks this turns
+        // the old O(n^2) neighbour scan into roughly O(n).
+        let params = self.params;
+        let sep_dist_sq = params.separation_radius * params.separation_radius;
+        let coh_dist_sq = params.neighbor_radius * params.neighbor_radius;
+        let ali_dist_sq = params.neighbor_radius * params.neighbor_radius;
+        let cell = self.cell_size();
+        // Number of cells per axis across the toroidal world.  Cells are keyed
+        // by their wrapped index so a boid near the +x edge and one near the
+        // -x edge share the seam's column and become neighbour candidates.
+        let span = self.grid_span();
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, p) in position.iter().enumerate() {
+            if let Some(p) = p.as_ref() {
+                grid.entry(wrap_cell(cell_of(p, cell), span)).or_insert_with(Vec::new).push(i);
             }
+        }
 
-            for target in 0..boids_length {
-
-                if b == target {
-                    continue;
+        {
+            let acceleration = manager.column_mut::<ecs::Acceleration>();
+            for b in 0..boids_length {
+                let pos_b = match position[b] {
+                    Some(p) => p,
+                    None => continue,
+                };
+                let mut s_force = Vector2::new(0., 0.);
+                let mut c_force = Vector2::new(0., 0.);
+                let mut a_force = Vector2::new(0., 0.);
+
+                // Only scan the 3x3 block of cells around the boid, wrapping
+                // the cell coordinates so boids on opposite edges still see
+                // each other across the toroidal seam.
+                let (bcx, bcy) = cell_of(&pos_b, cell);
+                for target in neighbors(&grid, bcx, bcy, span) {
+                    if b == target {
+                        continue;
+                    }
+                    let pos_t = match position[target] {
+                        Some(p) => p,
+                        None => continue,
+                    };
+
+                    // Minimal-image displacement so the seam does not inflate
+                    // distances for boids that are toroidally close.
+                    let spare = toroidal_delta(pos_b, pos_t, self.bounds);
+                    let dist_squared = spare.x*spare.x + spare.y*spare.y;
+
+                    if dist_squared < sep_dist_sq {
+                        let dist = spare.norm();
+                        let force = 1. - (params.separation_radius - dist) / params.separation_radius;
+
+                        s_force += spare * force;
+                    } else {
+                        if dist_squared < coh_dist_sq {
+                            c_force += spare;
+                        }
+                        if dist_squared < ali_dist_sq {
+                            if let Some(vt) = velocity[target] {
+                                a_force += vt;
+                            }
+                        }
+                    }
                 }
 
-                let spare = self.position[b] - self.position[target];
-                let dist_squared = spare.x*spare.x + spare.y*spare.y;
-
-                if dist_squared < SEP_DIST_SQ {
-                    let dist = spare.norm();
-                    let force = 1. - (SEPARATION_DISTANCE - dist) / SEPARATION_DISTANCE;
+                if let Some(a) = acceleration[b].as_mut() {
+                    // separation
+                    let sep_length = s_force.len() as f32;
+                    let sep_vector = Vector2::new(s_force.x * params.separation_weight / sep_length, s_force.y * params.separation_weight / sep_length);
+                    a.0 += sep_vector;
+
+                    // cohesion
+                    let coh_length = c_force.len() as f32;
+                    let coh_vector = Vector2::new(-c_force.x * params.cohesion_weight / coh_length, -c_force.y * params.cohesion_weight / coh_length);
+                    a.0 += coh_vector;
+
+                    // alignment
+                    let ali_length = a_force.len() as f32;
+                    let ali_vector = Vector2::new(a_force.x * params.alignment_weight / ali_length, a_force.y * params.alignment_weight / ali_length);
+                    a.0 += ali_vector;
+
+                    // predator avoidance: steer away from any predator inside
+                    // the flee radius, harder the closer it is, so the flock
+                    // splits around the ship and regroups once it passes.
+                    for predator in &self.predators {
+                        let away = toroidal_delta(pos_b, *predator, self.bounds);
+                        let dist = away.norm();
+                        if dist < params.flee_radius && dist > 0.0 {
+                            let proximity = (params.flee_radius - dist) / params.flee_radius;
+                            a.0 += away / dist * (params.flee_weight * proximity);
+                        }
+                    }
+                }
+            }
+        }
 
-                    s_force += spare * force; // * 1000. / spare.norm().powf(2.);
-                } else {
-                    if dist_squared < COH_DIST_SQ {
-                        c_force += spare;
+        // Integrate: acceleration -> velocity -> position, with the flock's
+        // own acceleration/speed clamps.
+        let acceleration: Vec<Option<Vector2>> = manager.column::<ecs::Acceleration>().iter().map(|a| a.map(|a| a.0)).collect();
+        {
+            let velocity = manager.column_mut::<ecs::Velocity>();
+            for i in 0..velocity.len() {
+                if let (Some(v), Some(mut accel)) = (velocity[i].as_mut(), acceleration[i]) {
+                    if accel.norm() > params.max_force {
+                        accel = accel.normalize() * params.max_force;
+                    }
+                    v.0 += accel * dt;
+                    if v.0.norm() > params.max_speed {
+                        v.0 = v.0.normalize() * params.max_speed;
                     }
-                    if dist_squared < ALI_DIST_SQ {
-                        //println!("alignment: my velocity: [{},{}] -- target velocity: [{},{}]", self.velocity[b].x, self.velocity[b].y, self.velocity[target].x, self.velocity[target].y);
-                        a_force += self.velocity[target];
+                    if v.0.norm() < MIN_SPEED_LIMIT {
+                        v.0 = v.0.normalize() * MIN_SPEED_LIMIT;
                     }
                 }
             }
+        }
+        let velocity: Vec<Option<Vector2>> = manager.column::<ecs::Velocity>().iter().map(|v| v.map(|v| v.0)).collect();
+        let position = manager.column_mut::<ecs::Position>();
+        for i in 0..position.len() {
+            if let (Some(p), Some(v)) = (position[i].as_mut(), velocity[i]) {
+                p.0 += v * dt;
+            }
+        }
+    }
+}
+
+/// Which grid cell a position falls into.
+fn cell_of(p: &Point2, cell: f32) -> (i32, i32) {
+    ((p.x / cell).floor() as i32, (p.y / cell).floor() as i32)
+}
+
+/// Number of cells per axis spanning the toroidal world, at least one.  Zero
+/// bounds (before the first frame sets them) collapse to a single cell.
+fn grid_span(bounds: Vector2, cell: f32) -> (i32, i32) {
+    let sx = (bounds.x / cell).round() as i32;
+    let sy = (bounds.y / cell).round() as i32;
+    (sx.max(1), sy.max(1))
+}
+
+/// Wrap a raw cell coordinate into `[0, span)` on each axis, so cells on
+/// opposite edges of the world map onto the same key.
+fn wrap_cell((cx, cy): (i32, i32), (sx, sy): (i32, i32)) -> (i32, i32) {
+    (((cx % sx) + sx) % sx, ((cy % sy) + sy) % sy)
+}
+
+/// Indices of every boid in the 3x3 block of cells centred on `(cx, cy)`, with
+/// the neighbour cells wrapped around the toroidal seam so edge boids still
+/// see each other.  Wrapped keys are de-duplicated for the degenerate case of
+/// a world only one or two cells wide.
+fn neighbors(grid: &HashMap<(i32, i32), Vec<usize>>, cx: i32, cy: i32, span: (i32, i32)) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut seen: Vec<(i32, i32)> = Vec::with_capacity(9);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let key = wrap_cell((cx + dx, cy + dy), span);
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            if let Some(bucket) = grid.get(&key) {
+                out.extend_from_slice(bucket);
+            }
+        }
+    }
+    out
+}
+
+/// Displacement from `to` to `from` taking the shortest path around the
+/// toroidal world of the given size.
+fn toroidal_delta(from: Point2, to: Point2, bounds: Vector2) -> Vector2 {
+    let mut d = from - to;
+    if bounds.x > 0.0 {
+        if d.x > bounds.x / 2.0 {
+            d.x -= bounds.x;
+        } else if d.x < -bounds.x / 2.0 {
+            d.x += bounds.x;
+        }
+    }
+    if bounds.y > 0.0 {
+        if d.y > bounds.y / 2.0 {
+            d.y -= bounds.y;
+        } else if d.y < -bounds.y / 2.0 {
+            d.y += bounds.y;
+        }
+    }
+    d
+}
+
+pub struct BoidComponent {
+    world: ecs::Manager,
+    flocking: FlockingSystem,
+    forces: ecs::ForceSystem,
+    wrapping: ecs::WrapSystem,
+    /// Real time carried over between fixed steps.
+    accumulator: f32,
+    /// Fraction of a step left over, in `[0, 1)`, for render interpolation.
+    alpha: f32,
+}
 
-            // separation
-            let sep_length = s_force.len() as f32;
-            let sep_vector = Vector2::new(s_force.x * SEPARATION_FORCE / sep_length, s_force.y * SEPARATION_FORCE / sep_length);
-            self.acceleration[b] += sep_vector;
+impl BoidComponent {
 
-            // cohesion
-            let coh_length = c_force.len() as f32;
-            let coh_vector = Vector2::new(-c_force.x * COHESION_FORCE / coh_length, -c_force.y * COHESION_FORCE / coh_length);
-            self.acceleration[b] += coh_vector;
+    pub fn new() -> BoidComponent {
+        BoidComponent {
+            world: ecs::Manager::new(),
+            flocking: FlockingSystem { bounds: Vector2::new(0., 0.), params: FlockParams::default(), predators: Vec::new() },
+            forces: ecs::ForceSystem { field: ecs::ForceField::new() },
+            wrapping: ecs::WrapSystem { bounds: Vector2::new(0., 0.) },
+            accumulator: 0.0,
+            alpha: 0.0,
+        }
+    }
 
-            // alignment
-            let ali_length = a_force.len() as f32;
-            let ali_vector = Vector2::new(a_force.x * COHESION_FORCE / ali_length, a_force.y * ALIGNMENT_FORCE / ali_length);
-            self.acceleration[b] += ali_vector;
+    /// Mutable access to the live flocking parameters, so the debug overlay
+    /// can tune the flock at runtime.
+    pub fn params_mut(&mut self) -> &mut FlockParams {
+        &mut self.flocking.params
+    }
 
-            //println!("SEPARATION_FORCE: {} -- s_force.x: {} -- s_force.u: {} -- sep_length: {}", SEPARATION_FORCE, s_force.x, s_force.y, sep_length);
-            //println!("[b={}] ---- sep_vector: [{},{}] -- coh_vector: [{},{}] -- ali_vector: [{},{}]", b, sep_vector.x, sep_vector.y, coh_vector.x, coh_vector.y, ali_vector.x, ali_vector.y);
+    pub fn init(&mut self, rng: &mut Rand32, count: usize, screen_size: &Vector2) {
+        for _ in 0..count {
+            self.spawn_random(rng);
+        }
+        for _ in 0..NUM_ATTRACTORS {
+            self.spawn_attractor(rng, screen_size);
         }
+    }
 
-        for i in 0..self.acceleration.len() {
-            if self.acceleration[i].norm() > ACCELERATION_LIMIT {
-                self.acceleration[i] = self.acceleration[i].normalize() * ACCELERATION_LIMIT;
-            }
+    pub fn spawn_attractor(&mut self, rng: &mut Rand32, screen_size: &Vector2) -> usize {
+        let pos = Point2::new(screen_size.x * rng.rand_float() - screen_size.x/2f32, screen_size.y * rng.rand_float() - screen_size.y/2f32);
+        self.forces.field.add_source(ecs::ForceSource {
+            pos,
+            radius: ATTRACTOR_RADIUS,
+            strength: ATTRACTOR_FORCE,
+        })
+    }
 
-            self.velocity[i] += self.acceleration[i] * dt;
-            if self.velocity[i].norm() > SPEED_LIMIT {
-                self.velocity[i] = self.velocity[i].normalize() * SPEED_LIMIT;
-            }
-            if self.velocity[i].norm() < MIN_SPEED_LIMIT {
-                self.velocity[i] = self.velocity[i].normalize() * MIN_SPEED_LIMIT;
-            }
+    pub fn spawn_random(&mut self, rng: &mut Rand32) -> Entity {
+        let e = self.world.create_entity();
+        self.world.add_component_direct(e, ecs::Position(Point2::new(100. * rng.rand_float(), 100. * rng.rand_float())));
+        self.world.add_component_direct(e, ecs::Velocity(Vector2::new(400. * rng.rand_float() - 200., 400. * rng.rand_float() - 200.)));
+        self.world.add_component_direct(e, ecs::Acceleration(Vector2::new(0., 0.)));
+        self.world.add_component_direct(e, ecs::Wrapping);
+        e
+    }
 
-            self.position[i] += self.velocity[i] * dt;
-            math::wrap_actor_position(&mut self.position[i], &screen_size);
+    pub fn update(&mut self, real_dt: f32, predators: &[Point2], screen_size: &Vector2) {
+        self.wrapping.bounds = *screen_size;
+        self.flocking.bounds = *screen_size;
+        self.flocking.predators.clear();
+        self.flocking.predators.extend_from_slice(predators);
+
+        // Step the flock in fixed `DT` increments, keeping any leftover time
+        // as `alpha` for the renderer to interpolate with.
+        self.accumulator += real_dt;
+        while self.accumulator >= ecs::DT {
+            self.forces.update(&mut self.world, ecs::DT);
+            self.flocking.update(&mut self.world, ecs::DT);
+            self.wrapping.update(&mut self.world, ecs::DT);
+            self.accumulator -= ecs::DT;
         }
+        self.alpha = self.accumulator / ecs::DT;
     }
 
     pub fn draw(&mut self,
                 ctx: &mut Context,
                 assets: &super::Assets,
                 world_coords: (u32, u32)) -> GameResult<()> {
-        for b in 0..self.position.len() {
-            let (screen_w, screen_h) = world_coords;
-            let position = super::math::world_to_screen_coords(screen_w, screen_h, &self.position[b]);
-            let image = &assets.player_image;
-            let drawparams = graphics::DrawParam {
-                dest: position,
-                rotation: math::angle_from_vec(&self.velocity[b]),
-                offset: graphics::Point2::new(0.5, 0.5),
-                ..Default::default()
-            };
-            graphics::draw_ex(ctx, image, drawparams)?;
+        let positions = self.world.column::<ecs::Position>();
+        let prev_positions = self.world.column::<ecs::PrevPosition>();
+        let velocities = self.world.column::<ecs::Velocity>();
+        for b in 0..positions.len() {
+            if let (Some(pos), Some(vel)) = (positions[b].as_ref(), velocities[b].as_ref()) {
+                let (screen_w, screen_h) = world_coords;
+                // Interpolate between the previous and current step positions,
+                // snapping across the toroidal seam so wrapped boids don't
+                // streak back over the whole playfield for the `alpha` frame.
+                let bounds = Vector2::new(screen_w as f32, screen_h as f32);
+                let drawn = match prev_positions.get(b).and_then(|p| p.as_ref()) {
+                    Some(prev) => math::lerp_wrapped(&prev.0, &pos.0, self.alpha, &bounds),
+                    None => pos.0,
+                };
+                let position = super::math::world_to_screen_coords(screen_w, screen_h, &drawn);
+                let image = assets.image("player");
+                let drawparams = graphics::DrawParam {
+                    dest: position,
+                    rotation: math::angle_from_vec(&vel.0),
+                    offset: graphics::Point2::new(0.5, 0.5),
+                    ..Default::default()
+                };
+                graphics::draw_ex(ctx, image, drawparams)?;
+            }
         }
 
-        for i in 0..self.attractors.position.len() {
+        for source in self.forces.field.sources() {
             let (screen_w, screen_h) = world_coords;
-            let position = super::math::world_to_screen_coords(screen_w, screen_h, &self.attractors.position[i]);
+            let position = super::math::world_to_screen_coords(screen_w, screen_h, &source.pos);
 
-            graphics::circle(ctx, graphics::DrawMode::Line(1.), position, self.attractors.radius[i], 1f32)?;
+            graphics::circle(ctx, graphics::DrawMode::Line(1.), position, source.radius, 1f32)?;
         }
 
         Ok(())
@@ -0,0 +1,92 @@
+//! Persisted window and gameplay settings.
+//!
+//! `main` used to hard-code an 800x800 window and the flock size lived in a
+//! `const`.  Those now come from a `config.toml` read at launch; if the file
+//! is missing we write the defaults back out so there is always something for
+//! the user to edit.  The same settings drive audio volume and are rewritten
+//! whenever the player saves (or takes a screenshot).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use toml;
+
+/// The on-disk settings file, relative to the working directory.
+pub const CONFIG_PATH: &str = "config.toml";
+
+/// Everything the game lets the user configure without recompiling.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Config {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub flock_size: usize,
+    /// Let the trained neural-net autopilot fly the ship instead of the
+    /// keyboard.
+    #[serde(default)]
+    pub autopilot: bool,
+    /// Where the autopilot genome is loaded from (and trained into when the
+    /// file is missing).
+    #[serde(default = "default_autopilot_genome")]
+    pub autopilot_genome: String,
+}
+
+/// Default path for the persisted autopilot genome.
+fn default_autopilot_genome() -> String {
+    "pilot.genome".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            width: 800,
+            height: 800,
+            fullscreen: false,
+            vsync: true,
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            flock_size: 100,
+            autopilot: false,
+            autopilot_genome: default_autopilot_genome(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the settings from `path`, falling back to the defaults (and
+    /// writing them back out) when the file is missing or unreadable.
+    pub fn load_or_default(path: &str) -> Config {
+        match fs::read_to_string(path) {
+            Ok(contents) => match toml::from_str(&contents) {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    println!("Could not parse {}: {}; using defaults", path, e);
+                    Config::default()
+                }
+            },
+            Err(_) => {
+                let cfg = Config::default();
+                if let Err(e) = cfg.save(path) {
+                    println!("Could not write default config to {}: {}", path, e);
+                }
+                cfg
+            }
+        }
+    }
+
+    /// Serialise the settings back to `path`.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let contents = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fs::write(Path::new(path), contents)
+    }
+
+    /// The effective playback gain for sound effects: master times sfx.
+    pub fn effective_sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+}
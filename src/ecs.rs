@@ -0,0 +1,433 @@
+//! A small but honest entity-component system.
+//!
+//! `actors.rs` used to admit up front that it was "not *quite* making a real
+//! entity-component system".  This module makes good on that: entities are
+//! plain integer handles, components live in type-erased columns keyed by
+//! their `TypeId`, and behaviour lives in `System`s that iterate over the
+//! entities carrying the components they care about.
+//!
+//! The point is to stop hand-rolling parallel `Vec`s (position / velocity /
+//! acceleration in the boids, player / shots / rocks in the actors) and to
+//! share movement, screen-wrapping and timed-life decay between every kind of
+//! game object instead of copy-pasting `update_actor_position` around.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use ggez::graphics::{Point2, Vector2};
+
+use math;
+
+/// A handle to an entity.  It is just an index into the component columns, so
+/// it is cheap to copy and store.
+pub type Entity = usize;
+
+/// The fixed physics timestep, in seconds.  Simulation always advances in
+/// whole `DT` increments regardless of the display frame rate.
+pub const DT: f32 = 1.0 / 60.0;
+
+/// *********************************************************************
+/// Components.  These are deliberately tiny new-types so that "has a
+/// velocity" and "has a hit-box" are separate, queryable facts rather than
+/// fields that every object is forced to carry.
+/// **********************************************************************
+
+#[derive(Clone, Copy, Debug)]
+pub struct Position(pub Point2);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Velocity(pub Vector2);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Acceleration(pub Vector2);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Facing {
+    pub angle: f32,
+    pub ang_vel: f32,
+}
+
+/// Marks an entity that should be wrapped back onto the screen when it leaves
+/// the playfield.
+#[derive(Clone, Copy, Debug)]
+pub struct Wrapping;
+
+/// A countdown, in seconds, after which the entity is dead.  (Shots use this;
+/// the boids do not carry it.)
+#[derive(Clone, Copy, Debug)]
+pub struct TimedLife(pub f32);
+
+/// The radius of the entity's bounding circle, used for collision.
+#[derive(Clone, Copy, Debug)]
+pub struct Collider(pub f32);
+
+/// The entity's position at the start of the most recent fixed physics step.
+/// `draw` interpolates between this and the current `Position` so motion stays
+/// smooth even when the display refreshes between steps.
+#[derive(Clone, Copy, Debug)]
+pub struct PrevPosition(pub Point2);
+
+/// The entity's facing at the start of the most recent fixed step, for the
+/// same render-time interpolation as `PrevPosition`.
+#[derive(Clone, Copy, Debug)]
+pub struct PrevFacing(pub f32);
+
+/// Hit points.  An entity at or below zero is dead and gets reaped.
+#[derive(Clone, Copy, Debug)]
+pub struct Health(pub f32);
+
+/// The logical sprite name an entity draws with, resolved against the asset
+/// registry at draw time.  This replaces keying the image off the `ActorType`
+/// enum, so new sprites are a manifest entry rather than a new `match` arm.
+#[derive(Clone, Debug)]
+pub struct Sprite(pub String);
+
+/// *********************************************************************
+/// Type-erased component storage.  Each component type gets one column, a
+/// `Vec<Option<T>>` with one slot per entity index ever allocated, dead ones
+/// included.
+/// **********************************************************************
+
+trait Storage: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Extend the column by one slot (holding no component) so it stays the
+    /// same length as the entity list.
+    fn push_empty(&mut self);
+    /// Clear the component at `index`, so a recycled entity slot does not
+    /// inherit the previous occupant's components.
+    fn clear_slot(&mut self, index: usize);
+}
+
+struct Column<T> {
+    data: Vec<Option<T>>,
+}
+
+impl<T: 'static> Storage for Column<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn push_empty(&mut self) {
+        self.data.push(None);
+    }
+    fn clear_slot(&mut self, index: usize) {
+        if let Some(slot) = self.data.get_mut(index) {
+            *slot = None;
+        }
+    }
+}
+
+/// *********************************************************************
+/// The manager owns the entities and every component column.
+/// **********************************************************************
+pub struct Manager {
+    /// Number of entity slots that have ever been allocated.  Columns are
+    /// kept this long.
+    count: usize,
+    alive: Vec<bool>,
+    /// Indices of killed entities, reused by `create_entity` so a game that
+    /// spawns and destroys continuously does not grow the columns without
+    /// bound.
+    free: Vec<Entity>,
+    columns: HashMap<TypeId, Box<dyn Storage>>,
+}
+
+impl Manager {
+    pub fn new() -> Self {
+        Manager {
+            count: 0,
+            alive: Vec::new(),
+            free: Vec::new(),
+            columns: HashMap::new(),
+        }
+    }
+
+    /// Allocate an entity, reusing a dead slot when one is free and otherwise
+    /// growing every existing column by one empty slot so the indices line up.
+    pub fn create_entity(&mut self) -> Entity {
+        if let Some(id) = self.free.pop() {
+            self.alive[id] = true;
+            // Wipe any components left behind by the slot's previous occupant.
+            for col in self.columns.values_mut() {
+                col.clear_slot(id);
+            }
+            return id;
+        }
+        let id = self.count;
+        self.count += 1;
+        self.alive.push(true);
+        for col in self.columns.values_mut() {
+            col.push_empty();
+        }
+        id
+    }
+
+    /// Attach a component to an entity, creating the column for `T` the first
+    /// time it is used.
+    pub fn add_component_direct<T: 'static>(&mut self, entity: Entity, component: T) {
+        let count = self.count;
+        let col = self
+            .columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Column::<T> {
+                    data: (0..count).map(|_| None).collect(),
+                })
+            });
+        let col = col
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+            .expect("component column type mismatch");
+        col.data[entity] = Some(component);
+    }
+
+    /// The backing column for `T`, or an empty slice if no entity has ever
+    /// had a `T`.  Systems iterate over these by index.
+    pub fn column<T: 'static>(&self) -> &[Option<T>] {
+        match self.columns.get(&TypeId::of::<T>()) {
+            Some(col) => &col.as_any().downcast_ref::<Column<T>>().unwrap().data,
+            None => &[],
+        }
+    }
+
+    /// Mutable access to the column for `T`, creating it if necessary.
+    pub fn column_mut<T: 'static>(&mut self) -> &mut Vec<Option<T>> {
+        let count = self.count;
+        let col = self
+            .columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Column::<T> {
+                    data: (0..count).map(|_| None).collect(),
+                })
+            });
+        &mut col
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+            .expect("component column type mismatch")
+            .data
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.column::<T>().get(entity).and_then(|c| c.as_ref())
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity).cloned().unwrap_or(false)
+    }
+
+    pub fn kill(&mut self, entity: Entity) {
+        if let Some(flag) = self.alive.get_mut(entity) {
+            // Only reclaim a slot the first time it dies, so a double-kill
+            // does not hand the same index out twice.
+            if *flag {
+                *flag = false;
+                self.free.push(entity);
+            }
+        }
+    }
+
+    /// All entity handles that have ever been created.  Callers combine this
+    /// with `is_alive` / component queries to get the set they want.
+    pub fn entities(&self) -> ::std::ops::Range<usize> {
+        0..self.count
+    }
+}
+
+/// *********************************************************************
+/// Systems.  A system is a chunk of behaviour that advances the world by
+/// `dt` seconds; `Manager` hands it every component column it needs.
+/// **********************************************************************
+pub trait System {
+    fn update(&mut self, manager: &mut Manager, dt: f32);
+}
+
+/// Newtonian integration shared by every moving entity: acceleration feeds
+/// velocity, velocity feeds position.  `max_speed` caps the result the same
+/// way `actors.rs` used to cap `MAX_PHYSICS_VEL` inline.
+pub struct MovementSystem {
+    pub max_speed: f32,
+}
+
+impl System for MovementSystem {
+    fn update(&mut self, manager: &mut Manager, dt: f32) {
+        snapshot_previous(manager);
+        let accel = manager.column::<Acceleration>().to_vec();
+        {
+            let vel = manager.column_mut::<Velocity>();
+            for i in 0..vel.len() {
+                if let Some(v) = vel[i].as_mut() {
+                    if let Some(Some(a)) = accel.get(i) {
+                        v.0 += a.0 * dt;
+                    }
+                    let norm_sq = v.0.norm_squared();
+                    if norm_sq > self.max_speed.powi(2) {
+                        v.0 = v.0 / norm_sq.sqrt() * self.max_speed;
+                    }
+                }
+            }
+        }
+        let vel = manager.column::<Velocity>().to_vec();
+        let pos = manager.column_mut::<Position>();
+        for i in 0..pos.len() {
+            if let (Some(p), Some(Some(v))) = (pos[i].as_mut(), vel.get(i)) {
+                p.0 += v.0 * dt;
+            }
+        }
+        let facing = manager.column_mut::<Facing>();
+        for f in facing.iter_mut().flatten() {
+            f.angle += f.ang_vel;
+        }
+    }
+}
+
+/// Record each entity's current position and facing into its `PrevPosition` /
+/// `PrevFacing` components before a fixed step mutates them, so the renderer
+/// has both endpoints to interpolate between.
+pub fn snapshot_previous(manager: &mut Manager) {
+    let pos = manager.column::<Position>().to_vec();
+    {
+        let prev = manager.column_mut::<PrevPosition>();
+        for i in 0..pos.len() {
+            if let Some(p) = pos[i] {
+                prev[i] = Some(PrevPosition(p.0));
+            }
+        }
+    }
+    let facing = manager.column::<Facing>().to_vec();
+    let prev = manager.column_mut::<PrevFacing>();
+    for i in 0..facing.len() {
+        if let Some(f) = facing[i] {
+            prev[i] = Some(PrevFacing(f.angle));
+        }
+    }
+}
+
+/// Wraps every `Wrapping` entity back onto a screen of the given size, so an
+/// object leaving one edge re-enters on the opposite one.
+pub struct WrapSystem {
+    pub bounds: Vector2,
+}
+
+impl System for WrapSystem {
+    fn update(&mut self, manager: &mut Manager, _dt: f32) {
+        let bounds = self.bounds;
+        let wraps = manager.column::<Wrapping>().to_vec();
+        let pos = manager.column_mut::<Position>();
+        for i in 0..pos.len() {
+            if wraps.get(i).map_or(false, |w| w.is_some()) {
+                if let Some(p) = pos[i].as_mut() {
+                    math::wrap_actor_position(&mut p.0, &bounds);
+                }
+            }
+        }
+    }
+}
+
+/// Decays `TimedLife` components and kills whatever runs out.
+pub struct TimedLifeSystem;
+
+impl System for TimedLifeSystem {
+    fn update(&mut self, manager: &mut Manager, dt: f32) {
+        let mut expired = Vec::new();
+        {
+            let lives = manager.column_mut::<TimedLife>();
+            for i in 0..lives.len() {
+                if let Some(life) = lives[i].as_mut() {
+                    life.0 -= dt;
+                    if life.0 <= 0.0 {
+                        expired.push(i);
+                    }
+                }
+            }
+        }
+        for e in expired {
+            manager.kill(e);
+        }
+    }
+}
+
+/// *********************************************************************
+/// Global and local forces.  Following the usual apply-gravity-then-integrate
+/// pattern, a `ForceField` adds a constant acceleration (gravity), any number
+/// of radial attractor / repulsor sources with an inverse-distance falloff,
+/// and a linear drag term, all onto an entity's velocity.  The
+/// `MovementSystem`'s speed clamp still runs afterwards.
+/// **********************************************************************
+
+/// A single radial force source.  Positive `strength` attracts, negative
+/// repels; the effect falls off with distance and vanishes past `radius`.
+#[derive(Clone, Copy, Debug)]
+pub struct ForceSource {
+    pub pos: Point2,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+pub struct ForceField {
+    pub gravity: Vector2,
+    pub friction: f32,
+    sources: Vec<ForceSource>,
+}
+
+impl ForceField {
+    pub fn new() -> ForceField {
+        ForceField {
+            gravity: Vector2::new(0.0, 0.0),
+            friction: 0.0,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Add a radial source and return its index for later removal.
+    pub fn add_source(&mut self, source: ForceSource) -> usize {
+        self.sources.push(source);
+        self.sources.len() - 1
+    }
+
+    pub fn remove_source(&mut self, index: usize) {
+        if index < self.sources.len() {
+            self.sources.remove(index);
+        }
+    }
+
+    pub fn sources(&self) -> &[ForceSource] {
+        &self.sources
+    }
+
+    /// Apply gravity, every radial source and drag to one velocity.
+    fn apply(&self, pos: Point2, vel: &mut Vector2, dt: f32) {
+        *vel += self.gravity * dt;
+        for source in &self.sources {
+            let toward = source.pos - pos;
+            let dist = toward.norm();
+            if dist < source.radius && dist > 0.0 {
+                // Inverse-distance falloff along the unit vector to the source.
+                let accel = toward / dist * (source.strength / dist);
+                *vel += accel * dt;
+            }
+        }
+        *vel -= *vel * self.friction * dt;
+    }
+}
+
+/// Applies a `ForceField` to every entity that has both a position and a
+/// velocity, so rocks, shots and boids all feel the same fields.
+pub struct ForceSystem {
+    pub field: ForceField,
+}
+
+impl System for ForceSystem {
+    fn update(&mut self, manager: &mut Manager, dt: f32) {
+        let pos = manager.column::<Position>().to_vec();
+        let vel = manager.column_mut::<Velocity>();
+        for i in 0..vel.len() {
+            if let (Some(v), Some(&Some(p))) = (vel[i].as_mut(), pos.get(i)) {
+                self.field.apply(p.0, &mut v.0, dt);
+            }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! The boids/asteroids gameplay, as a `Scene` on the stack.
+//!
+//! This is the loop `MainState` used to run inline.  It now owns only the
+//! simulation state; images, sounds and the window size come from the
+//! `SharedContext` passed to each callback.
+
+use ggez::{Context, GameResult};
+use ggez::event::{Keycode, Mod};
+use ggez::graphics;
+use ggez::graphics::Vector2;
+use ggez::timer;
+
+use actors::{self, GameEvent};
+use ai;
+use boids_mgr;
+use config;
+use scene::{Scene, SharedContext, Transition};
+
+pub struct GameScene {
+    actor_mgr: actors::ActorManager,
+    boid_mgr: boids_mgr::BoidComponent,
+    level: i32,
+    score: i32,
+    input: actors::InputState,
+    player_shot_timeout: f32,
+    gui_dirty: bool,
+    frames: usize,
+    fps_display: graphics::Text,
+    /// Live-tuning overlay for the flocking params, toggled with F1.
+    debug_overlay: bool,
+    /// Which `FlockParams` field the overlay arrows currently adjust.
+    selected_param: usize,
+    /// When set, the neural-net autopilot flies the ship in place of the
+    /// keyboard `InputState`.
+    autopilot: Option<ai::NeuralController>,
+}
+
+/// The flocking fields the overlay steps through, with the label shown and the
+/// increment applied by the arrow keys.
+const PARAM_LABELS: [(&str, f32); 9] = [
+    ("separation_weight", 0.25),
+    ("alignment_weight", 0.05),
+    ("cohesion_weight", 0.05),
+    ("separation_radius", 5.0),
+    ("neighbor_radius", 5.0),
+    ("max_speed", 5.0),
+    ("max_force", 5.0),
+    ("flee_radius", 5.0),
+    ("flee_weight", 0.1),
+];
+
+impl GameScene {
+    pub fn new(shared: &mut SharedContext, ctx: &mut Context) -> GameResult<GameScene> {
+        let fps_disp = graphics::Text::new(ctx, "fps", &shared.assets.font)?;
+
+        let actor_mgr = actors::ActorManager::new();
+        let mut boid_mgr = boids_mgr::BoidComponent::new();
+        boid_mgr.init(&mut shared.rng, shared.config.flock_size, &Vector2::new(shared.screen_width as f32, shared.screen_height as f32));
+
+        // Optionally hand the controls to the trained autopilot, loading its
+        // genome from disk or training (and saving) one the first time.
+        let autopilot = if shared.config.autopilot {
+            let path = &shared.config.autopilot_genome;
+            let genome = match ai::load_genome(path) {
+                Ok(g) if g.len() == ai::NeuralNet::genome_len() => g,
+                _ => {
+                    println!("Training autopilot (this may take a moment)...");
+                    let g = ai::train(&ai::TrainParams::default());
+                    if let Err(e) = ai::save_genome(&g, path) {
+                        println!("Could not save autopilot genome to {}: {}", path, e);
+                    }
+                    g
+                }
+            };
+            Some(ai::NeuralController::new(ai::NeuralNet::from_genome(&genome)))
+        } else {
+            None
+        };
+
+        Ok(GameScene {
+            actor_mgr,
+            boid_mgr,
+            level: 0,
+            score: 0,
+            input: actors::InputState::default(),
+            player_shot_timeout: 0.0,
+            gui_dirty: true,
+            frames: 0,
+            fps_display: fps_disp,
+            debug_overlay: false,
+            selected_param: 0,
+            autopilot,
+        })
+    }
+
+    /// Current value of the selected flocking field, for the overlay display.
+    fn param_value(&mut self, index: usize) -> f32 {
+        let p = self.boid_mgr.params_mut();
+        match index {
+            0 => p.separation_weight,
+            1 => p.alignment_weight,
+            2 => p.cohesion_weight,
+            3 => p.separation_radius,
+            4 => p.neighbor_radius,
+            5 => p.max_speed,
+            6 => p.max_force,
+            7 => p.flee_radius,
+            _ => p.flee_weight,
+        }
+    }
+
+    /// Nudge the selected field by `delta`, clamping to non-negative values.
+    fn adjust_param(&mut self, index: usize, delta: f32) {
+        let p = self.boid_mgr.params_mut();
+        let field = match index {
+            0 => &mut p.separation_weight,
+            1 => &mut p.alignment_weight,
+            2 => &mut p.cohesion_weight,
+            3 => &mut p.separation_radius,
+            4 => &mut p.neighbor_radius,
+            5 => &mut p.max_speed,
+            6 => &mut p.max_force,
+            7 => &mut p.flee_radius,
+            _ => &mut p.flee_weight,
+        };
+        *field = (*field + delta).max(0.0);
+    }
+
+    fn fire_player_shot(&mut self) -> Vec<GameEvent> {
+        self.player_shot_timeout = actors::PLAYER_SHOT_TIME;
+        self.actor_mgr.fire_player_shot_helper()
+    }
+
+    fn update_ui(&mut self, ctx: &mut Context, shared: &SharedContext) {
+        self.frames += 1;
+        if (self.frames % 100) == 0 {
+            let fps_str = format!("FPS: {:.*}", 2, timer::get_fps(ctx));
+            let fps_text = graphics::Text::new(ctx, &fps_str, &shared.assets.font).unwrap();
+            self.fps_display = fps_text;
+        }
+    }
+}
+
+impl Scene for GameScene {
+    fn update(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<Transition> {
+        let seconds = timer::duration_to_f64(timer::get_delta(ctx)) as f32;
+
+        // Collect this frame's gameplay events for audio (and later HUD).
+        let mut events: Vec<GameEvent> = Vec::new();
+
+        // The autopilot, when enabled, produces the frame's input from the
+        // world state; otherwise the keyboard `InputState` drives the ship.
+        let input = match self.autopilot {
+            Some(ref controller) => controller.update(&self.actor_mgr),
+            None => self.input.clone(),
+        };
+
+        self.player_shot_timeout -= seconds;
+        if input.fire && self.player_shot_timeout < 0.0 {
+            events.extend(self.fire_player_shot());
+        }
+
+        {
+            self.actor_mgr.update(seconds, &input, shared.screen_width as f32, shared.screen_height as f32);
+
+            let hits = self.actor_mgr.handle_collisions();
+            let num_hits = hits
+                .iter()
+                .filter(|e| if let GameEvent::RockDestroyed { .. } = **e { true } else { false })
+                .count() as i32;
+            if num_hits > 0 {
+                self.score += num_hits;
+                self.gui_dirty = true;
+            }
+            events.extend(hits);
+
+            self.actor_mgr.clear_dead_stuff();
+
+            if self.actor_mgr.rocks_are_empty() {
+                self.level += 1;
+                self.gui_dirty = true;
+                self.actor_mgr.when_rocks_empty(&mut shared.rng, self.level + 5);
+            }
+        }
+
+        if self.gui_dirty {
+            self.update_ui(ctx, shared);
+            self.gui_dirty = false;
+        }
+
+        // Feed the flock the ship (and rocks) as predators so it scatters
+        // when the player flies through it.
+        let (player_pos, _, _) = self.actor_mgr.player_kinematics();
+        let mut predators = vec![player_pos];
+        predators.extend(self.actor_mgr.rock_colliders().into_iter().map(|(pos, _)| pos));
+        self.boid_mgr.update(seconds, &predators, &Vector2::new(shared.screen_width as f32, shared.screen_height as f32));
+
+        shared.sound_bank.play_events(ctx, &events);
+
+        // Pop ourselves off the stack on death; with nothing left the game
+        // exits.  A dedicated game-over scene can slot in here later.
+        if self.actor_mgr.player_is_dead() {
+            println!("Game over!");
+            return Ok(Transition::Pop);
+        }
+
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx);
+
+        {
+            let assets = &mut shared.assets;
+            let coords = (shared.screen_width, shared.screen_height);
+            self.boid_mgr.draw(ctx, assets, coords)?;
+        }
+
+        let fps_dest = graphics::Point2::new(400.0, 10.0);
+        graphics::draw(ctx, &self.fps_display, fps_dest, 0.0)?;
+
+        if self.debug_overlay {
+            for i in 0..PARAM_LABELS.len() {
+                let (name, _) = PARAM_LABELS[i];
+                let marker = if i == self.selected_param { ">" } else { " " };
+                let line = format!("{} {}: {:.3}", marker, name, self.param_value(i));
+                let text = graphics::Text::new(ctx, &line, &shared.assets.font)?;
+                let dest = graphics::Point2::new(10.0, 30.0 + i as f32 * 20.0);
+                graphics::draw(ctx, &text, dest, 0.0)?;
+            }
+        }
+
+        graphics::present(ctx);
+        timer::yield_now();
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, shared: &mut SharedContext, ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) -> Transition {
+        // While the tuning overlay is up the arrow keys drive it instead of
+        // the ship: up/down pick a field, left/right step its value.
+        if self.debug_overlay {
+            match keycode {
+                Keycode::F1 => self.debug_overlay = false,
+                Keycode::Up => {
+                    self.selected_param = (self.selected_param + PARAM_LABELS.len() - 1) % PARAM_LABELS.len();
+                }
+                Keycode::Down => {
+                    self.selected_param = (self.selected_param + 1) % PARAM_LABELS.len();
+                }
+                Keycode::Left => {
+                    let delta = PARAM_LABELS[self.selected_param].1;
+                    self.adjust_param(self.selected_param, -delta);
+                }
+                Keycode::Right => {
+                    let delta = PARAM_LABELS[self.selected_param].1;
+                    self.adjust_param(self.selected_param, delta);
+                }
+                _ => (),
+            }
+            return Transition::None;
+        }
+
+        match keycode {
+            Keycode::F1 => {
+                self.debug_overlay = true;
+            }
+            Keycode::Up => {
+                self.input.yaxis = 1.0;
+            }
+            Keycode::Left => {
+                self.input.xaxis = -1.0;
+            }
+            Keycode::Right => {
+                self.input.xaxis = 1.0;
+            }
+            Keycode::Space => {
+                self.input.fire = true;
+            }
+            Keycode::P => {
+                let img = graphics::screenshot(ctx).expect("Could not take screenshot");
+                img.encode(ctx, graphics::ImageFormat::Png, "/screenshot.png")
+                    .expect("Could not save screenshot");
+                save_config(shared);
+            }
+            Keycode::F2 => save_config(shared),
+            Keycode::Escape => return Transition::Pop,
+            _ => (), // Do nothing
+        }
+        Transition::None
+    }
+
+    fn key_up_event(&mut self, _shared: &mut SharedContext, _ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) -> Transition {
+        match keycode {
+            Keycode::Up => {
+                self.input.yaxis = 0.0;
+            }
+            Keycode::Left | Keycode::Right => {
+                self.input.xaxis = 0.0;
+            }
+            Keycode::Space => {
+                self.input.fire = false;
+            }
+            _ => (), // Do nothing
+        }
+        Transition::None
+    }
+}
+
+/// Write the current settings back to disk, re-applying the sfx volume first
+/// so any live change takes effect.  Called from the save action and whenever
+/// the player takes a screenshot.
+fn save_config(shared: &mut SharedContext) {
+    shared.sound_bank.set_volume(shared.config.effective_sfx_volume());
+    if let Err(e) = shared.config.save(config::CONFIG_PATH) {
+        println!("Could not save config: {}", e);
+    }
+}
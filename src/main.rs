@@ -3,66 +3,88 @@
 //! non-trivial enough to be interesting.
 
 extern crate ggez;
+extern crate oorandom;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
-use ggez::{Context, ContextBuilder, GameResult};
-use ggez::audio;
+use ggez::{Context, ContextBuilder, GameError, GameResult};
 use ggez::conf;
 use ggez::event::{self, Keycode, Mod, EventHandler};
 use ggez::graphics;
-use ggez::graphics::{Vector2};
-use ggez::timer;
 
+use std::collections::HashMap;
 use std::env;
+use std::io::Read;
 use std::path;
 
 mod actors;
+mod ai;
+mod audio;
+mod config;
+mod ecs;
+mod game;
 mod math;
 mod boids_mgr;
+mod scene;
 
-use actors::{ Actor, ActorType };
+use config::Config;
 
 /// **********************************************************************
 /// So that was the real meat of our game.  Now we just need a structure
 /// to contain the images, sounds, etc. that we need to hang on to; this
-/// is our "asset management system".  All the file names and such are
-/// just hard-coded.
+/// is our "asset management system".  Rather than hard-coding one field
+/// (and one `match` arm) per file, we load a manifest under `resources/`
+/// that maps logical names to paths, so swapping the file behind a sprite or
+/// sound is a data change instead of a code change.  Which keys get used is
+/// still decided in code (the spawn functions and `audio`).
 /// **********************************************************************
 
+/// The `resources/assets.toml` manifest: logical names to resource paths.
+#[derive(Deserialize)]
+pub struct AssetManifest {
+    pub font: String,
+    pub images: HashMap<String, String>,
+    pub sounds: HashMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Read and parse the manifest from the ggez filesystem.
+    pub fn load(ctx: &mut Context) -> GameResult<AssetManifest> {
+        let mut file = ctx.filesystem.open("/assets.toml")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        toml::from_str(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("assets manifest: {}", e)))
+    }
+}
+
+/// A handle-based image registry: images are looked up by their logical name
+/// at draw time, so `draw_actor` never needs to know the concrete sprite set.
 pub struct Assets {
-    player_image: graphics::Image,
-    shot_image: graphics::Image,
-    rock_image: graphics::Image,
+    images: HashMap<String, graphics::Image>,
     font: graphics::Font,
-    shot_sound: audio::Source,
-    hit_sound: audio::Source,
 }
 
 impl Assets {
-    fn new(ctx: &mut Context) -> GameResult<Assets> {
-        let player_image = graphics::Image::new(ctx, "/player.png")?;
-        let shot_image = graphics::Image::new(ctx, "/shot.png")?;
-        let rock_image = graphics::Image::new(ctx, "/rock.png")?;
-        let font = graphics::Font::new(ctx, "/DejaVuSerif.ttf", 18)?;
-
-        let shot_sound = audio::Source::new(ctx, "/pew.ogg")?;
-        let hit_sound = audio::Source::new(ctx, "/boom.ogg")?;
-        Ok(Assets {
-            player_image,
-            shot_image,
-            rock_image,
-            font,
-            shot_sound,
-            hit_sound,
-        })
+    fn new(ctx: &mut Context, manifest: &AssetManifest) -> GameResult<Assets> {
+        let mut images = HashMap::new();
+        for (name, path) in &manifest.images {
+            images.insert(name.clone(), graphics::Image::new(ctx, path)?);
+        }
+        let font = graphics::Font::new(ctx, &manifest.font, 18)?;
+
+        Ok(Assets { images, font })
     }
 
-    fn actor_image(&self, actor: &Actor) -> &graphics::Image {
-        match actor.tag {
-            ActorType::Player => &self.player_image,
-            ActorType::Rock => &self.rock_image,
-            ActorType::Shot => &self.shot_image,
-        }
+    /// The image registered under `key`, panicking if the manifest is missing
+    /// an entry a spawned entity asked for.
+    fn image(&self, key: &str) -> &graphics::Image {
+        self.images
+            .get(key)
+            .unwrap_or_else(|| panic!("no image registered for key {:?}", key))
     }
 }
 
@@ -77,82 +99,40 @@ impl Assets {
 /// this small it hardly matters.
 /// **********************************************************************
 
+/// The top-level `EventHandler` now just holds the shared context and the
+/// scene stack, forwarding every ggez callback to whichever scene is on top.
 struct MainState {
-    actor_mgr: actors::ActorManager,
-    boid_mgr: boids_mgr::BoidComponent,
-    level: i32,
-    score: i32,
-    assets: Assets,
-    screen_width: u32,
-    screen_height: u32,
-    input: actors::InputState,
-    player_shot_timeout: f32,
-    gui_dirty: bool,
-    frames: usize,
-    // score_display: graphics::Text,
-    // level_display: graphics::Text,
-    fps_display: graphics::Text,
+    shared: scene::SharedContext,
+    stack: scene::SceneStack,
 }
 
 impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<MainState> {
+    fn new(ctx: &mut Context, seed: u64, config: Config) -> GameResult<MainState> {
         ctx.print_resource_stats();
         graphics::set_background_color(ctx, (0, 0, 0, 255).into());
 
         println!("Game resource path: {:?}", ctx.filesystem);
+        println!("Flock seed: {}", seed);
 
         print_instructions();
 
-        let assets = Assets::new(ctx)?;
-        // let score_disp = graphics::Text::new(ctx, "score", &assets.font)?;
-        // let level_disp = graphics::Text::new(ctx, "level", &assets.font)?;
-        let fps_disp = graphics::Text::new(ctx, "fps", &assets.font)?;
-
-        let actor_mgr = actors::ActorManager::new();
-        let mut boid_mgr = boids_mgr::BoidComponent::new();
-        boid_mgr.init();
+        let manifest = AssetManifest::load(ctx)?;
+        let assets = Assets::new(ctx, &manifest)?;
+        let sound_bank = audio::SoundBank::new(ctx, &manifest, config.effective_sfx_volume())?;
 
-        let s = MainState {
-            actor_mgr,
-            boid_mgr,
-            level: 0,
-            score: 0,
+        let mut shared = scene::SharedContext {
             assets,
-            screen_width: ctx.conf.window_mode.width,
-            screen_height: ctx.conf.window_mode.height,
-            input: actors::InputState::default(),
-            player_shot_timeout: 0.0,
-            gui_dirty: true,
-            frames: 0,
-            // score_display: score_disp,
-            // level_display: level_disp,
-            fps_display: fps_disp,
+            sound_bank,
+            screen_width: config.width,
+            screen_height: config.height,
+            rng: oorandom::Rand32::new(seed),
+            config,
         };
 
-        Ok(s)
-    }
-
-    fn fire_player_shot(&mut self) {
-        self.player_shot_timeout = actors::PLAYER_SHOT_TIME;
-        self.actor_mgr.fire_player_shot_helper();
-        let _ = self.assets.shot_sound.play();
-    }
+        let game = game::GameScene::new(&mut shared, ctx)?;
+        let stack = scene::SceneStack::new(Box::new(game));
 
-    fn update_ui(&mut self, ctx: &mut Context) {
-        // let score_str = format!("Score: {}", self.score);
-        // let score_text = graphics::Text::new(ctx, &score_str, &self.assets.font).unwrap();
-        // self.score_display = score_text;
-        //
-        // let level_str = format!("Level: {}", self.level);
-        // let level_text = graphics::Text::new(ctx, &level_str, &self.assets.font).unwrap();
-        // self.level_display = level_text;
-
-        self.frames += 1;
-        if (self.frames % 100) == 0 {
-            let fps_str = format!("FPS: {:.*}", 2, ggez::timer::get_fps(ctx));
-            let fps_text = graphics::Text::new(ctx, &fps_str, &self.assets.font).unwrap();
-            self.fps_display = fps_text;
-        }
+        Ok(MainState { shared, stack })
     }
 }
 
@@ -169,18 +149,31 @@ fn print_instructions() {
     println!();
 }
 
+/// Seed for the flock/asteroid RNG, so a run is reproducible.  Taken from the
+/// `BOIDS_SEED` environment variable or the first command-line argument, in
+/// that order, falling back to a fixed default when neither is supplied.
+fn flock_seed() -> u64 {
+    env::var("BOIDS_SEED")
+        .ok()
+        .or_else(|| env::args().nth(1))
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0x5eed_b0a1)
+}
+
 pub fn draw_actor(
     assets: &Assets,
     ctx: &mut Context,
-    actor: &Actor,
+    sprite_key: &str,
+    actor_pos: graphics::Point2,
+    facing: f32,
     world_coords: (u32, u32),
 ) -> GameResult<()> {
     let (screen_w, screen_h) = world_coords;
-    let pos = math::world_to_screen_coords(screen_w, screen_h, &actor.pos);
-    let image = assets.actor_image(actor);
+    let pos = math::world_to_screen_coords(screen_w, screen_h, &actor_pos);
+    let image = assets.image(sprite_key);
     let drawparams = graphics::DrawParam {
         dest: pos,
-        rotation: actor.facing as f32,
+        rotation: facing,
         offset: graphics::Point2::new(0.5, 0.5),
         ..Default::default()
     };
@@ -194,131 +187,28 @@ pub fn draw_actor(
 /// **********************************************************************
 impl EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        const DESIRED_FPS: u32 = 60;
-
-        while timer::check_update_time(ctx, DESIRED_FPS) {
-            const SECONDS: f32 = 1.0 / (DESIRED_FPS as f32);
-
-            // Update the player state based on the user input.
-            //player_handle_input(&mut self.player, &self.input, seconds);
-            self.player_shot_timeout -= SECONDS;
-            if self.input.fire && self.player_shot_timeout < 0.0 {
-                self.fire_player_shot();
-            }
-
-            // update all the actors
-            {
-                self.actor_mgr.update(SECONDS, &self.input, self.screen_width as f32, self.screen_height as f32);
-
-                let num_hits = self.actor_mgr.handle_collisions();
-                if num_hits > 0 {
-                    self.score += num_hits;
-                    self.gui_dirty = true;
-                    let _ = self.assets.hit_sound.play();
-                }
-
-                self.actor_mgr.clear_dead_stuff();
-
-                //self.check_for_level_respawn();
-                if self.actor_mgr.rocks_are_empty() {
-                    self.level += 1;
-                    self.gui_dirty = true;
-                    self.actor_mgr.when_rocks_empty(self.level + 5);
-                }
-            }
-
-            // Using a gui_dirty flag here is a little messy but fine here.
-            if self.gui_dirty {
-                self.update_ui(ctx);
-                self.gui_dirty = false;
-            }
-
-            // Finally we check for our end state.
-            // I want to have a nice death screen eventually, but for now we just quit.
-            if self.actor_mgr.player_is_dead() {
-                println!("Game over!");
-                let _ = ctx.quit();
-            }
-
-            // update boids
-            self.boid_mgr.update(SECONDS, Vector2::new(self.screen_width as f32, self.screen_height as f32));
+        self.stack.update(&mut self.shared, ctx)?;
+        if self.stack.is_empty() {
+            let _ = ctx.quit();
         }
-
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        // Our drawing is quite simple.
-        // Just clear the screen...
-        graphics::clear(ctx);
-
-        // Loop over all objects drawing them...
-        {
-            let assets = &mut self.assets;
-            let coords = (self.screen_width, self.screen_height);
-            //self.actor_mgr.draw(ctx, assets, coords)?;
-            self.boid_mgr.draw(ctx, assets, coords)?;
-        }
-
-        // And draw the GUI elements in the right places.
-        // let level_dest = graphics::Point2::new(10.0, 10.0);
-        // graphics::draw(ctx, &self.level_display, level_dest, 0.0)?;
-        // let score_dest = graphics::Point2::new(200.0, 10.0);
-        // graphics::draw(ctx, &self.score_display, score_dest, 0.0)?;
-        let fps_dest = graphics::Point2::new(400.0, 10.0);
-        graphics::draw(ctx, &self.fps_display, fps_dest, 0.0)?;
-
-
-        // Then we flip the screen...
-        graphics::present(ctx);
-
-        // And yield the timeslice
-        // This tells the OS that we're done using the CPU but it should get back to this program as soon as it can.
-        // This ideally prevents the game from using 100% CPU all the time even if vsync is off.
-        // The actual behavior can be a little platform-specific.
-        timer::yield_now();
-        Ok(())
+        self.stack.draw(&mut self.shared, ctx)
     }
 
-    // Handle key events.  These just map keyboard events
-    // and alter our input state appropriately.
-    fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
-        match keycode {
-            Keycode::Up => {
-                self.input.yaxis = 1.0;
-            }
-            Keycode::Left => {
-                self.input.xaxis = -1.0;
-            }
-            Keycode::Right => {
-                self.input.xaxis = 1.0;
-            }
-            Keycode::Space => {
-                self.input.fire = true;
-            }
-            Keycode::P => {
-                let img = graphics::screenshot(ctx).expect("Could not take screenshot");
-                img.encode(ctx, graphics::ImageFormat::Png, "/screenshot.png")
-                    .expect("Could not save screenshot");
-            }
-            Keycode::Escape => ctx.quit().unwrap(),
-            _ => (), // Do nothing
+    // Handle key events.  These just forward to the active scene, which maps
+    // them onto its own state and may request a transition.
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+        self.stack.key_down_event(&mut self.shared, ctx, keycode, keymod, repeat);
+        if self.stack.is_empty() {
+            let _ = ctx.quit();
         }
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
-        match keycode {
-            Keycode::Up => {
-                self.input.yaxis = 0.0;
-            }
-            Keycode::Left | Keycode::Right => {
-                self.input.xaxis = 0.0;
-            }
-            Keycode::Space => {
-                self.input.fire = false;
-            }
-            _ => (), // Do nothing
-        }
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+        self.stack.key_up_event(&mut self.shared, ctx, keycode, keymod, repeat);
     }
 }
 
@@ -327,9 +217,20 @@ impl EventHandler for MainState {
 /// `ggez::event::run()` with our `EventHandler` type.
 /// **********************************************************************
 pub fn main() {
+    let config = Config::load_or_default(config::CONFIG_PATH);
+
+    let fullscreen = if config.fullscreen {
+        conf::FullscreenType::True
+    } else {
+        conf::FullscreenType::Off
+    };
     let mut cb = ContextBuilder::new("astroblasto", "ggez")
-        .window_setup(conf::WindowSetup::default().title("Astroblasto!"))
-        .window_mode(conf::WindowMode::default().dimensions(800, 800));
+        .window_setup(conf::WindowSetup::default().title("Astroblasto!").vsync(config.vsync))
+        .window_mode(
+            conf::WindowMode::default()
+                .dimensions(config.width, config.height)
+                .fullscreen_type(fullscreen),
+        );
 
     // We add the CARGO_MANIFEST_DIR/resources to the filesystems paths so
     // we we look in the cargo project for files.
@@ -347,7 +248,7 @@ pub fn main() {
 
     let ctx = &mut cb.build().unwrap();
 
-    match MainState::new(ctx) {
+    match MainState::new(ctx, flock_seed(), config) {
         Err(e) => {
             println!("Could not load game!");
             println!("Error: {}", e);
@@ -1,6 +1,7 @@
 extern crate rand;
 
 use ggez::graphics::{Point2, Vector2};
+use oorandom::Rand32;
 
 /// *********************************************************************
 /// Basic stuff, make some helpers for vector functions.
@@ -20,13 +21,33 @@ pub fn angle_from_vec(vec: &Vector2) -> f32 {
     vec.x.atan2(vec.y)
 }
 
-/// Just makes a random `Vector2` with the given max magnitude.
-pub fn random_vec(max_magnitude: f32) -> Vector2 {
-    let angle = rand::random::<f32>() * 2.0 * ::std::f32::consts::PI;
-    let mag = rand::random::<f32>() * max_magnitude;
+/// Just makes a random `Vector2` with the given max magnitude, drawn from an
+/// explicit seeded PRNG so the result is reproducible for a given seed.
+pub fn random_vec_seeded(rng: &mut Rand32, max_magnitude: f32) -> Vector2 {
+    let angle = rng.rand_float() * 2.0 * ::std::f32::consts::PI;
+    let mag = rng.rand_float() * max_magnitude;
     vec_from_angle(angle) * (mag)
 }
 
+/// Linearly interpolate between two points, used to render entities at their
+/// in-between position when the display runs faster than the fixed physics
+/// step.
+pub fn lerp_point(a: &Point2, b: &Point2, t: f32) -> Point2 {
+    Point2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+/// Interpolate between the previous and current step positions, but snap to the
+/// current one when the gap is larger than half the world on either axis.  A
+/// jump that big means the entity wrapped around the toroidal edge this step,
+/// and lerping across it would streak the sprite all the way over the screen.
+pub fn lerp_wrapped(prev: &Point2, curr: &Point2, t: f32, bounds: &Vector2) -> Point2 {
+    if (curr.x - prev.x).abs() > bounds.x / 2.0 || (curr.y - prev.y).abs() > bounds.y / 2.0 {
+        *curr
+    } else {
+        lerp_point(prev, curr, t)
+    }
+}
+
 /// Translates the world coordinate system, which
 /// has Y pointing up and the origin at the center,
 /// to the screen coordinate system, which has Y
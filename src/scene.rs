@@ -0,0 +1,114 @@
+//! A small scene-stack on top of ggez's single `EventHandler`.
+//!
+//! `MainState` used to hard-code one gameplay loop and quit on death.  A
+//! `Scene` is now one screen's worth of behaviour (the boids/asteroids scene,
+//! and later a title menu, a pause overlay or a game-over screen); a
+//! `SceneStack` owns a stack of them and forwards ggez's callbacks to the top
+//! one.  Each callback returns a `Transition` telling the stack how to change.
+//!
+//! Shared resources that should be loaded once (images, sounds, the window
+//! size) live in `SharedContext`, which is handed to every scene.
+
+use ggez::{Context, GameResult};
+use ggez::event::{Keycode, Mod};
+use oorandom::Rand32;
+
+use super::Assets;
+use super::audio::SoundBank;
+use super::config::Config;
+
+/// Resources and global state shared by every scene, loaded once at startup.
+pub struct SharedContext {
+    pub assets: Assets,
+    pub sound_bank: SoundBank,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    /// Seeded PRNG driving all spawn randomness, so a given seed reproduces
+    /// the same flock layout and asteroid field every launch.
+    pub rng: Rand32,
+    /// The user settings loaded at launch, rewritten on save / screenshot.
+    pub config: Config,
+}
+
+/// What a scene wants the stack to do after a callback.
+pub enum Transition {
+    /// Stay on the current scene.
+    None,
+    /// Push a new scene on top, pausing the current one.
+    Push(Box<dyn Scene>),
+    /// Drop the current scene, returning to the one beneath.
+    Pop,
+    /// Swap the current scene for another.
+    Replace(Box<dyn Scene>),
+}
+
+/// One screen of the game.  Every callback receives the shared context and the
+/// raw ggez `Context`.
+pub trait Scene {
+    fn update(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<Transition>;
+    fn draw(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<()>;
+    fn key_down_event(&mut self, shared: &mut SharedContext, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) -> Transition;
+    fn key_up_event(&mut self, shared: &mut SharedContext, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) -> Transition;
+}
+
+/// Owns the live stack of scenes and applies the transitions they return.
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(initial: Box<dyn Scene>) -> SceneStack {
+        SceneStack { scenes: vec![initial] }
+    }
+
+    /// True once every scene has been popped; the game then exits.
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    pub fn update(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<()> {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(shared, ctx)?,
+            None => Transition::None,
+        };
+        self.apply(transition);
+        Ok(())
+    }
+
+    pub fn draw(&mut self, shared: &mut SharedContext, ctx: &mut Context) -> GameResult<()> {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.draw(shared, ctx)?;
+        }
+        Ok(())
+    }
+
+    pub fn key_down_event(&mut self, shared: &mut SharedContext, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.key_down_event(shared, ctx, keycode, keymod, repeat),
+            None => Transition::None,
+        };
+        self.apply(transition);
+    }
+
+    pub fn key_up_event(&mut self, shared: &mut SharedContext, ctx: &mut Context, keycode: Keycode, keymod: Mod, repeat: bool) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.key_up_event(shared, ctx, keycode, keymod, repeat),
+            None => Transition::None,
+        };
+        self.apply(transition);
+    }
+}